@@ -0,0 +1,86 @@
+//!
+//! Fuzzes InstanceCreateInfo construction and instance creation. Run against a null or lavapipe
+//! driver, e.g. `VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json cargo fuzz run
+//! instance_create_info`
+//!
+#![no_main]
+
+use std::sync::LazyLock;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use pibaf::vk::{
+    Extension, Instance,
+    extension::{self, AvailableExtension, AvailableExtensions},
+    instance::InstanceCreateInfo,
+};
+
+static AVAILABLE_EXTENSIONS: LazyLock<Vec<AvailableExtension>> =
+    LazyLock::new(extension::enumerate);
+
+#[derive(Arbitrary, Debug)]
+struct Input<'a> {
+    application_name: Option<&'a [u8]>,
+    application_version: Option<u32>,
+    engine_name: Option<&'a [u8]>,
+    engine_version: Option<u32>,
+    api_version: u32,
+    /// Only passed on if VK_KHR_portability_enumeration is enabled, as the flag is invalid
+    /// without it
+    enumerate_portability: Option<bool>,
+    /// Bit i enables the i-th available extension
+    extension_mask: u64,
+}
+
+fuzz_target!(|input: Input<'_>| {
+    let mut extensions = AvailableExtensions::default();
+    for (i, available) in AVAILABLE_EXTENSIONS.iter().take(64).enumerate() {
+        if input.extension_mask & (1 << i) != 0 {
+            extensions.add(available.clone());
+        }
+    }
+    let extension_count = extensions.extensions().len();
+    let has_portability = extensions
+        .extensions()
+        .iter()
+        .any(|available| available.extension() == Extension::KhrPortabilityEnumeration);
+    let enumerate_portability = input.enumerate_portability.filter(|_| has_portability);
+
+    let info = InstanceCreateInfo::builder()
+        .maybe_application_name(input.application_name)
+        .maybe_application_version(input.application_version)
+        .maybe_engine_name(input.engine_name)
+        .maybe_engine_version(input.engine_version)
+        .api_version(input.api_version)
+        .maybe_enumerate_portability(enumerate_portability)
+        .extensions(extensions)
+        .build();
+
+    // Names with interior nulls must be rejected
+    let has_nul = |name: Option<&[u8]>| name.is_some_and(|name| name.contains(&0));
+    let Ok(info) = info else {
+        assert!(has_nul(input.application_name) || has_nul(input.engine_name));
+        return;
+    };
+
+    {
+        let raw = info.create_raw();
+        let create_info = raw.vk_instance_create_info();
+        assert_eq!(
+            create_info.enabled_extension_count as usize,
+            extension_count
+        );
+        assert_eq!(create_info.enabled_layer_count, 0);
+        // Safety: the application info points into raw, which is alive
+        let application_info = unsafe { &*create_info.p_application_info };
+        assert_eq!(application_info.api_version, input.api_version);
+    }
+
+    // Invalid combinations must surface as errors, not crashes
+    if let Ok(instance) = Instance::try_create_vk_instance(info) {
+        assert_eq!(
+            instance.api_version().raw(),
+            input.api_version.max(ash::vk::API_VERSION_1_0)
+        );
+    }
+});
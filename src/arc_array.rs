@@ -1,31 +1,122 @@
-use std::{
-    cell::UnsafeCell,
-    mem::MaybeUninit,
-    sync::atomic::{AtomicU64, Ordering, fence},
-};
+use std::mem::MaybeUninit;
+
+use sync::{AtomicU64, Ordering, UnsafeCell, fence};
+
+/// Synchronization primitives, replaced by loom's when testing with
+/// `RUSTFLAGS="--cfg loom" cargo test --lib arc_array`. Run the models in a debug build, so that
+/// overflows of the ref counts are caught
+#[cfg(not(loom))]
+mod sync {
+    pub(super) use std::sync::atomic::{AtomicU64, Ordering, fence};
+
+    /// std UnsafeCell with loom's closure-based access api
+    pub(super) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+    impl<T> UnsafeCell<T> {
+        pub(super) const fn new(data: T) -> Self {
+            Self(std::cell::UnsafeCell::new(data))
+        }
+
+        pub(super) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+            f(self.0.get())
+        }
+
+        pub(super) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+            f(self.0.get())
+        }
+    }
+}
+
+#[cfg(loom)]
+mod sync {
+    pub(super) use loom::{
+        cell::UnsafeCell,
+        sync::atomic::{AtomicU64, Ordering, fence},
+    };
+}
+
+/// Ref count of an item that is being initialized or dropped
+pub(crate) const BUSY: u64 = u64::MAX;
+/// Ref count of a slot whose initialization panicked in a poisoning array
+pub(crate) const POISONED: u64 = u64::MAX - 1;
+
+/// Common interface of the atomically ref-counting containers, so handles can be built on top of
+/// either of them
+pub trait ArcRegistry<T> {
+    /// Identifies an item in the registry
+    type Index: Copy;
+
+    /// Initializes a free element with the given function and returns its index if one is
+    /// available
+    fn acquire_and_init(&self, init: impl FnOnce() -> T) -> Option<Self::Index>;
+
+    /// Returns a reference to the item with the given index
+    /// # Safety
+    /// should only call this with an index of an initialized and not dropped item
+    unsafe fn get_ref(&self, index: Self::Index) -> &T;
+
+    /// Decrements item's ref count and drops if no more references are left
+    /// # Safety
+    /// dec_count should be called no more that once for each corresponding inc_count
+    unsafe fn dec_count(&self, index: Self::Index);
+
+    /// Increments item's ref count
+    fn inc_count(&self, index: Self::Index);
+
+    /// Increments item's ref count only if the item is alive. Returns true if the count was
+    /// incremented, in which case dec_count must be called for it later
+    fn try_inc_count(&self, index: Self::Index) -> bool;
+
+    /// Returns the number of alive items. Should only be used for diagnostics
+    fn live_count(&self) -> usize;
+}
 
 /// Atomically ref-counting indexed container for T
 pub struct UnsafeArcArray<const N: usize, T> {
     ref_counts: [AtomicU64; N],
     items: [UnsafeCell<MaybeUninit<T>>; N],
+    poison_on_panic: bool,
 }
 
-// Safety: Can only be modified using unsafe
-unsafe impl<const N: usize, T: Sync> Sync for UnsafeArcArray<N, T> {}
+/// Releases the slot claimed by acquire_and_init if the init function panics
+struct InitGuard<'a> {
+    ref_count: &'a AtomicU64,
+    poison: bool,
+}
+
+impl Drop for InitGuard<'_> {
+    fn drop(&mut self) {
+        let count = if self.poison { POISONED } else { 0 };
+        self.ref_count.store(count, Ordering::Release);
+    }
+}
+
+// Safety: Can only be modified using unsafe. T must be Send, as the item is dropped by whichever
+// thread releases the last reference
+unsafe impl<const N: usize, T: Send + Sync> Sync for UnsafeArcArray<N, T> {}
 
 impl<const N: usize, T> UnsafeArcArray<N, T> {
     /// Initializes the first free element with the given function and returns its index if such
-    /// element is present
+    /// element is present. If init panics, the element is freed again, or poisoned if the array
+    /// was created with new_poisoning
     pub fn acquire_and_init(&self, init: impl FnOnce() -> T) -> Option<usize> {
         for (idx, rc) in self.ref_counts.iter().enumerate() {
+            // Acquire pairs with the Release store in dec_count, so the previous item is fully
+            // dropped before it is overwritten
             if rc
-                .compare_exchange(0, 1, Ordering::AcqRel, Ordering::Relaxed)
+                .compare_exchange(0, BUSY, Ordering::Acquire, Ordering::Relaxed)
                 .is_ok()
             {
+                let guard = InitGuard {
+                    ref_count: rc,
+                    poison: self.poison_on_panic,
+                };
                 // Safety: The item did not have references exept this one
-                unsafe {
-                    (&mut *self.items[idx].get()).write(init());
-                }
+                self.items[idx].with_mut(|item| unsafe {
+                    (*item).write(init());
+                });
+                std::mem::forget(guard);
+                rc.store(1, Ordering::Release);
                 return Some(idx);
             }
         }
@@ -36,47 +127,162 @@ impl<const N: usize, T> UnsafeArcArray<N, T> {
     /// # Safety
     /// should only call this with an index of an initialized and not dropped item
     pub unsafe fn get_ref(&self, index: usize) -> &T {
-        unsafe { (&*self.items[index].get()).assume_init_ref() }
+        self.items[index].with(|item| unsafe { (*item).assume_init_ref() })
+    }
+
+    /// Calls f with a reference to the item with the given index. Unlike get_ref, the reference
+    /// does not outlive the cell access, so loom can check reads against concurrent drops
+    /// # Safety
+    /// should only call this with an index of an initialized and not dropped item
+    pub unsafe fn with_ref<R>(&self, index: usize, f: impl FnOnce(&T) -> R) -> R {
+        self.items[index].with(|item| f(unsafe { (*item).assume_init_ref() }))
     }
 
     /// Decrements item's ref count and drops if no more references are left  
     /// # Safety
     /// dec_count should be called no more that once for each corresponding inc_count
     pub unsafe fn dec_count(&self, index: usize) {
-        assert!(index < N);
+        let rc = &self.ref_counts[index];
 
-        let prev_count =
-            unsafe { self.ref_counts.get_unchecked(index) }.fetch_sub(1, Ordering::Relaxed);
-        debug_assert!(prev_count > 0);
-        if prev_count == 1 {
+        // The last reference moves the count to BUSY instead of 0, so that the slot can not be
+        // acquired again while the item is being dropped
+        let mut count = rc.load(Ordering::Relaxed);
+        loop {
+            debug_assert!(count > 0 && count < POISONED);
+            let new_count = if count == 1 { BUSY } else { count - 1 };
+            // Release makes this reference's uses of the item happen before the item is dropped
+            match rc.compare_exchange_weak(count, new_count, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(actual) => count = actual,
+            }
+        }
+
+        if count == 1 {
             // Safety: if no more references are left, the item should be dropped.
             fence(Ordering::Acquire);
-            unsafe {
-                (&mut *self.items.get_unchecked(index).get()).assume_init_drop();
-            };
+            self.items[index].with_mut(|item| unsafe { (*item).assume_init_drop() });
+            rc.store(0, Ordering::Release);
         }
     }
 
     /// Increments item's ref count
     pub fn inc_count(&self, index: usize) {
+        // Relaxed is enough, as the caller already holds a reference
         self.ref_counts[index].fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Increments item's ref count only if the item is alive. Returns true if the count was
+    /// incremented, in which case dec_count must be called for it later
+    pub fn try_inc_count(&self, index: usize) -> bool {
+        self.ref_counts[index]
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |count| {
+                // count + 1 must only be computed for alive items, BUSY would overflow
+                if count > 0 && count < POISONED {
+                    Some(count + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Returns item's ref count. The count may already be outdated when it is returned, so it
+    /// should only be used for diagnostics
+    pub fn ref_count(&self, index: usize) -> u64 {
+        self.ref_counts[index].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of alive items. Like ref_count, should only be used for diagnostics
+    pub fn live_count(&self) -> usize {
+        self.ref_counts
+            .iter()
+            .filter(|rc| !matches!(rc.load(Ordering::Relaxed), 0 | POISONED))
+            .count()
+    }
+
+    /// Checks if the item's initialization panicked. Poisoned items are never reused
+    pub fn is_poisoned(&self, index: usize) -> bool {
+        self.ref_counts[index].load(Ordering::Relaxed) == POISONED
+    }
+
+    /// Maximum number of items
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[cfg(not(loom))]
     pub const fn new() -> Self {
         Self {
             ref_counts: [const { AtomicU64::new(0) }; N],
             items: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+            poison_on_panic: false,
+        }
+    }
+
+    /// Creates an array that permanently poisons a slot if its initialization panics, instead of
+    /// freeing it
+    #[cfg(not(loom))]
+    pub const fn new_poisoning() -> Self {
+        Self {
+            poison_on_panic: true,
+            ..Self::new()
+        }
+    }
+
+    // loom's primitives can not be created in const context
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        Self {
+            ref_counts: std::array::from_fn(|_| AtomicU64::new(0)),
+            items: std::array::from_fn(|_| UnsafeCell::new(MaybeUninit::uninit())),
+            poison_on_panic: false,
+        }
+    }
+
+    #[cfg(loom)]
+    pub fn new_poisoning() -> Self {
+        Self {
+            poison_on_panic: true,
+            ..Self::new()
         }
     }
 }
 
+impl<const N: usize, T> ArcRegistry<T> for UnsafeArcArray<N, T> {
+    type Index = usize;
+
+    fn acquire_and_init(&self, init: impl FnOnce() -> T) -> Option<usize> {
+        Self::acquire_and_init(self, init)
+    }
+
+    unsafe fn get_ref(&self, index: usize) -> &T {
+        unsafe { Self::get_ref(self, index) }
+    }
+
+    unsafe fn dec_count(&self, index: usize) {
+        unsafe { Self::dec_count(self, index) }
+    }
+
+    fn inc_count(&self, index: usize) {
+        Self::inc_count(self, index)
+    }
+
+    fn try_inc_count(&self, index: usize) -> bool {
+        Self::try_inc_count(self, index)
+    }
+
+    fn live_count(&self) -> usize {
+        Self::live_count(self)
+    }
+}
+
 impl<const N: usize, T> Default for UnsafeArcArray<N, T> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod test {
     struct DropSet {
         v: i64,
@@ -105,7 +311,11 @@ mod test {
             )
         }
     }
-    use std::{cell::Cell, rc::Rc};
+    use std::{
+        cell::Cell,
+        panic::{AssertUnwindSafe, catch_unwind},
+        rc::Rc,
+    };
 
     use super::*;
     #[test]
@@ -136,5 +346,191 @@ mod test {
         assert!(idx4.is_none());
     }
 
-    // TODO: test better: multiple items, parallel access
+    #[test]
+    fn counts() {
+        let arr = UnsafeArcArray::<3, i64>::default();
+        assert_eq!(arr.capacity(), 3);
+        assert_eq!(arr.live_count(), 0);
+
+        let idx = arr.acquire_and_init(|| 1).unwrap();
+        arr.inc_count(idx);
+        assert_eq!(arr.ref_count(idx), 2);
+        assert_eq!(arr.live_count(), 1);
+
+        unsafe {
+            arr.dec_count(idx);
+            arr.dec_count(idx);
+        }
+        assert_eq!(arr.ref_count(idx), 0);
+        assert_eq!(arr.live_count(), 0);
+    }
+
+    #[test]
+    fn try_inc_count() {
+        let arr = UnsafeArcArray::<2, i64>::default();
+        let idx = arr.acquire_and_init(|| 1).unwrap();
+
+        assert!(arr.try_inc_count(idx));
+        assert_eq!(arr.ref_count(idx), 2);
+        unsafe {
+            arr.dec_count(idx);
+            arr.dec_count(idx);
+        }
+
+        assert!(!arr.try_inc_count(idx));
+        assert_eq!(arr.ref_count(idx), 0);
+    }
+
+    #[test]
+    fn init_panic_frees_slot() {
+        let arr = UnsafeArcArray::<1, i64>::new();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            arr.acquire_and_init(|| panic!("init failed"))
+        }));
+        assert!(res.is_err());
+
+        assert!(!arr.is_poisoned(0));
+        assert_eq!(arr.live_count(), 0);
+        assert_eq!(arr.acquire_and_init(|| 1), Some(0));
+    }
+
+    #[test]
+    fn init_panic_poisons_slot() {
+        let arr = UnsafeArcArray::<2, i64>::new_poisoning();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            arr.acquire_and_init(|| panic!("init failed"))
+        }));
+        assert!(res.is_err());
+
+        assert!(arr.is_poisoned(0));
+        assert!(!arr.try_inc_count(0));
+        assert_eq!(arr.live_count(), 0);
+        assert_eq!(arr.acquire_and_init(|| 1), Some(1));
+        assert_eq!(arr.acquire_and_init(|| 2), None);
+    }
+
+    #[test]
+    fn parallel_clone_and_drop() {
+        const THREADS: usize = 4;
+        // Miri is too slow for many iterations
+        const ITERATIONS: usize = if cfg!(miri) { 10 } else { 1000 };
+
+        let arr = UnsafeArcArray::<2, Vec<usize>>::new();
+        let idx = arr.acquire_and_init(|| vec![1, 2, 3]).unwrap();
+
+        std::thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    for _ in 0..ITERATIONS {
+                        arr.inc_count(idx);
+                        assert_eq!(unsafe { arr.get_ref(idx) }.len(), 3);
+                        unsafe { arr.dec_count(idx) };
+                    }
+                });
+            }
+        });
+
+        assert_eq!(arr.ref_count(idx), 1);
+        unsafe { arr.dec_count(idx) };
+        assert_eq!(arr.live_count(), 0);
+    }
+
+    #[test]
+    fn parallel_acquire() {
+        const ITERATIONS: usize = if cfg!(miri) { 10 } else { 1000 };
+
+        let arr = UnsafeArcArray::<3, Box<usize>>::new();
+
+        std::thread::scope(|s| {
+            for t in 0..3 {
+                let arr = &arr;
+                s.spawn(move || {
+                    for i in 0..ITERATIONS {
+                        // Each thread holds at most one item, so there is always a free slot
+                        let idx = arr
+                            .acquire_and_init(|| Box::new(t * ITERATIONS + i))
+                            .unwrap();
+                        assert_eq!(**unsafe { arr.get_ref(idx) }, t * ITERATIONS + i);
+                        unsafe { arr.dec_count(idx) };
+                    }
+                });
+            }
+        });
+
+        assert_eq!(arr.live_count(), 0);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_drop() {
+        loom::model(|| {
+            let arr = Arc::new(UnsafeArcArray::<1, Box<usize>>::new());
+            let idx = arr.acquire_and_init(|| Box::new(1)).unwrap();
+            arr.inc_count(idx);
+
+            let handle = {
+                let arr = arr.clone();
+                loom::thread::spawn(move || unsafe {
+                    assert_eq!(arr.with_ref(idx, |item| **item), 1);
+                    arr.dec_count(idx);
+                })
+            };
+            unsafe {
+                assert_eq!(arr.with_ref(idx, |item| **item), 1);
+                arr.dec_count(idx);
+            }
+            handle.join().unwrap();
+
+            assert_eq!(arr.live_count(), 0);
+        });
+    }
+
+    #[test]
+    fn acquire_while_dropping() {
+        loom::model(|| {
+            let arr = Arc::new(UnsafeArcArray::<1, Box<usize>>::new());
+            let idx = arr.acquire_and_init(|| Box::new(1)).unwrap();
+
+            let handle = {
+                let arr = arr.clone();
+                loom::thread::spawn(move || unsafe { arr.dec_count(idx) })
+            };
+            // Either the slot is still taken or it was fully dropped before reuse
+            if let Some(new_idx) = arr.acquire_and_init(|| Box::new(2)) {
+                unsafe {
+                    assert_eq!(arr.with_ref(new_idx, |item| **item), 2);
+                    arr.dec_count(new_idx);
+                }
+            }
+            handle.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn try_inc_while_dropping() {
+        loom::model(|| {
+            let arr = Arc::new(UnsafeArcArray::<1, Box<usize>>::new());
+            let idx = arr.acquire_and_init(|| Box::new(1)).unwrap();
+
+            let handle = {
+                let arr = arr.clone();
+                loom::thread::spawn(move || unsafe { arr.dec_count(idx) })
+            };
+            if arr.try_inc_count(idx) {
+                unsafe {
+                    assert_eq!(arr.with_ref(idx, |item| **item), 1);
+                    arr.dec_count(idx);
+                }
+            }
+            handle.join().unwrap();
+
+            assert_eq!(arr.live_count(), 0);
+        });
+    }
 }
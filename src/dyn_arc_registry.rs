@@ -0,0 +1,423 @@
+//!
+//! Heap allocated alternative to UnsafeArcArray for objects with unbounded counts. Works as a
+//! slab with generational keys, whose free slots are kept in several sharded free lists
+//!
+
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering, fence},
+    },
+};
+
+use crate::arc_array::{ArcRegistry, BUSY, POISONED};
+
+const FIRST_PAGE_SIZE: usize = 32;
+/// Page k holds FIRST_PAGE_SIZE << k slots, so this many pages cover the whole usize range
+const PAGE_COUNT: usize = (usize::BITS - FIRST_PAGE_SIZE.trailing_zeros()) as usize;
+/// Number of free lists. Slot i is returned to shard i % SHARD_COUNT
+const SHARD_COUNT: usize = 8;
+
+/// Counter used to spread threads over the free list shards
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Free list shard this thread looks at first when acquiring a slot
+    static HOME_SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % SHARD_COUNT;
+}
+
+struct Slot<T> {
+    ref_count: AtomicU64,
+    generation: AtomicU32,
+    item: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// Index of an item in a DynArcRegistry. Stays unique after the item is dropped and its slot is
+/// reused, so stale keys can be detected
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct RegistryKey {
+    index: usize,
+    generation: u32,
+}
+
+/// Atomically ref-counting container for T that grows on demand. Slots live in pages that are
+/// never moved or freed until the registry is dropped, so references to items stay valid while
+/// the registry grows
+pub struct DynArcRegistry<T> {
+    pages: [OnceLock<Box<[Slot<T>]>>; PAGE_COUNT],
+    /// Number of slots that were ever handed out
+    len: AtomicUsize,
+    /// Free slots, sharded so that threads acquiring and releasing slots rarely contend on the
+    /// same lock
+    free: [Mutex<Vec<usize>>; SHARD_COUNT],
+}
+
+// Safety: Can only be modified using unsafe
+unsafe impl<T: Send + Sync> Sync for DynArcRegistry<T> {}
+// Safety: Items are dropped on the thread dropping the registry
+unsafe impl<T: Send> Send for DynArcRegistry<T> {}
+
+impl<T> DynArcRegistry<T> {
+    pub const fn new() -> Self {
+        Self {
+            pages: [const { OnceLock::new() }; PAGE_COUNT],
+            len: AtomicUsize::new(0),
+            free: [const { Mutex::new(Vec::new()) }; SHARD_COUNT],
+        }
+    }
+
+    fn slot(&self, index: usize) -> &Slot<T> {
+        let page = (index + FIRST_PAGE_SIZE).ilog2() - FIRST_PAGE_SIZE.ilog2();
+        let offset = index + FIRST_PAGE_SIZE - (FIRST_PAGE_SIZE << page);
+        let page = self.pages[page as usize].get_or_init(|| {
+            (0..FIRST_PAGE_SIZE << page)
+                .map(|_| Slot {
+                    ref_count: AtomicU64::new(0),
+                    generation: AtomicU32::new(0),
+                    item: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect()
+        });
+        &page[offset]
+    }
+
+    /// Initializes a free element with the given function and returns its key. If init panics,
+    /// the element is freed again
+    pub fn acquire_and_init(&self, init: impl FnOnce() -> T) -> RegistryKey {
+        let index = self
+            .pop_free()
+            .unwrap_or_else(|| self.len.fetch_add(1, Ordering::Relaxed));
+        let slot = self.slot(index);
+
+        // Acquire pairs with the Release store in dec_count, so the previous item is fully
+        // dropped before it is overwritten
+        let claimed =
+            slot.ref_count
+                .compare_exchange(0, BUSY, Ordering::Acquire, Ordering::Relaxed);
+        debug_assert!(claimed.is_ok(), "free slot {index} is in use");
+
+        let guard = FreeGuard {
+            registry: self,
+            index,
+        };
+        // Safety: The slot was free, so there are no other references to the item
+        unsafe {
+            (*slot.item.get()).write(init());
+        }
+        std::mem::forget(guard);
+        slot.ref_count.store(1, Ordering::Release);
+
+        RegistryKey {
+            index,
+            generation: slot.generation.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns a reference to the item with the given key
+    /// # Safety
+    /// should only call this with a key of an initialized and not dropped item
+    pub unsafe fn get_ref(&self, key: RegistryKey) -> &T {
+        unsafe { (*self.slot(key.index).item.get()).assume_init_ref() }
+    }
+
+    /// Decrements item's ref count and drops if no more references are left
+    /// # Safety
+    /// dec_count should be called no more that once for each corresponding inc_count
+    pub unsafe fn dec_count(&self, key: RegistryKey) {
+        let slot = self.slot(key.index);
+
+        // Same as UnsafeArcArray::dec_count: the last reference moves the count to BUSY, so the
+        // slot can not be reused while the item is being dropped
+        let mut count = slot.ref_count.load(Ordering::Relaxed);
+        loop {
+            debug_assert!(count > 0 && count < POISONED);
+            let new_count = if count == 1 { BUSY } else { count - 1 };
+            match slot.ref_count.compare_exchange_weak(
+                count,
+                new_count,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => count = actual,
+            }
+        }
+
+        if count == 1 {
+            fence(Ordering::Acquire);
+            // Safety: no more references are left
+            unsafe { (*slot.item.get()).assume_init_drop() };
+            slot.generation.fetch_add(1, Ordering::Relaxed);
+            self.release(key.index);
+        }
+    }
+
+    /// Takes a free slot, starting with the calling thread's home shard and then trying the others
+    fn pop_free(&self) -> Option<usize> {
+        let home = HOME_SHARD.with(|shard| *shard);
+        (0..SHARD_COUNT).find_map(|i| {
+            self.free[(home + i) % SHARD_COUNT]
+                .lock()
+                .expect("DynArcRegistry free list is poisoned")
+                .pop()
+        })
+    }
+
+    /// Marks the slot as free and puts it to its free list shard
+    fn release(&self, index: usize) {
+        self.slot(index).ref_count.store(0, Ordering::Release);
+        self.free[index % SHARD_COUNT]
+            .lock()
+            .expect("DynArcRegistry free list is poisoned")
+            .push(index);
+    }
+
+    /// Increments item's ref count
+    pub fn inc_count(&self, key: RegistryKey) {
+        self.slot(key.index)
+            .ref_count
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Increments item's ref count only if the item is alive and the key is not stale. Returns
+    /// true if the count was incremented, in which case dec_count must be called for it later
+    pub fn try_inc_count(&self, key: RegistryKey) -> bool {
+        if key.index >= self.len.load(Ordering::Relaxed) {
+            return false;
+        }
+        let slot = self.slot(key.index);
+        let incremented = slot
+            .ref_count
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |count| {
+                // count + 1 must only be computed for alive items, BUSY would overflow
+                if count > 0 && count < POISONED {
+                    Some(count + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok();
+        if !incremented {
+            return false;
+        }
+
+        // The slot may hold a newer item than the key refers to. The generation can not change
+        // while the reference taken above is held
+        let generation = slot.generation.load(Ordering::Relaxed);
+        if generation != key.generation {
+            // Safety: the count was incremented above for the item with this generation
+            unsafe {
+                self.dec_count(RegistryKey {
+                    index: key.index,
+                    generation,
+                })
+            };
+            return false;
+        }
+        true
+    }
+
+    /// Returns the number of alive items. Should only be used for diagnostics
+    pub fn live_count(&self) -> usize {
+        (0..self.len.load(Ordering::Relaxed))
+            .filter(|&index| {
+                !matches!(
+                    self.slot(index).ref_count.load(Ordering::Relaxed),
+                    0 | POISONED
+                )
+            })
+            .count()
+    }
+}
+
+/// Frees the slot claimed by acquire_and_init if the init function panics
+struct FreeGuard<'a, T> {
+    registry: &'a DynArcRegistry<T>,
+    index: usize,
+}
+
+impl<T> Drop for FreeGuard<'_, T> {
+    fn drop(&mut self) {
+        self.registry.release(self.index);
+    }
+}
+
+impl<T> Drop for DynArcRegistry<T> {
+    fn drop(&mut self) {
+        for index in 0..*self.len.get_mut() {
+            let slot = self.slot(index);
+            if !matches!(slot.ref_count.load(Ordering::Acquire), 0 | POISONED) {
+                // Safety: the item is initialized and nothing can reference it anymore, as the
+                // registry is borrowed mutably
+                unsafe { (*slot.item.get()).assume_init_drop() };
+            }
+        }
+    }
+}
+
+impl<T> ArcRegistry<T> for DynArcRegistry<T> {
+    type Index = RegistryKey;
+
+    fn acquire_and_init(&self, init: impl FnOnce() -> T) -> Option<RegistryKey> {
+        Some(Self::acquire_and_init(self, init))
+    }
+
+    unsafe fn get_ref(&self, key: RegistryKey) -> &T {
+        unsafe { Self::get_ref(self, key) }
+    }
+
+    unsafe fn dec_count(&self, key: RegistryKey) {
+        unsafe { Self::dec_count(self, key) }
+    }
+
+    fn inc_count(&self, key: RegistryKey) {
+        Self::inc_count(self, key)
+    }
+
+    fn try_inc_count(&self, key: RegistryKey) -> bool {
+        Self::try_inc_count(self, key)
+    }
+
+    fn live_count(&self) -> usize {
+        Self::live_count(self)
+    }
+}
+
+impl<T> Default for DynArcRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        panic::{AssertUnwindSafe, catch_unwind},
+        rc::Rc,
+    };
+
+    use super::*;
+    use crate::arc_array::UnsafeArcArray;
+
+    #[test]
+    fn basic() {
+        let registry = DynArcRegistry::new();
+        let key = registry.acquire_and_init(|| 5);
+        registry.inc_count(key);
+        assert_eq!(unsafe { *registry.get_ref(key) }, 5);
+        assert_eq!(registry.live_count(), 1);
+
+        unsafe {
+            registry.dec_count(key);
+            registry.dec_count(key);
+        }
+        assert_eq!(registry.live_count(), 0);
+    }
+
+    #[test]
+    fn grows_past_first_page() {
+        let registry = DynArcRegistry::new();
+        let keys: Vec<_> = (0..FIRST_PAGE_SIZE * 4)
+            .map(|i| registry.acquire_and_init(|| i))
+            .collect();
+
+        assert_eq!(registry.live_count(), FIRST_PAGE_SIZE * 4);
+        for (i, &key) in keys.iter().enumerate() {
+            assert_eq!(unsafe { *registry.get_ref(key) }, i);
+        }
+    }
+
+    #[test]
+    fn stale_key() {
+        let registry = DynArcRegistry::new();
+        let old = registry.acquire_and_init(|| 1);
+        unsafe { registry.dec_count(old) };
+
+        let new = registry.acquire_and_init(|| 2);
+        assert_eq!(old.index, new.index);
+        assert_ne!(old, new);
+
+        assert!(!registry.try_inc_count(old));
+        assert!(registry.try_inc_count(new));
+        assert_eq!(registry.live_count(), 1);
+    }
+
+    #[test]
+    fn try_inc_busy_slot() {
+        let registry = DynArcRegistry::new();
+        let key = registry.acquire_and_init(|| 1);
+        registry
+            .slot(key.index)
+            .ref_count
+            .store(BUSY, Ordering::Relaxed);
+        assert!(!registry.try_inc_count(key));
+        registry
+            .slot(key.index)
+            .ref_count
+            .store(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn reuses_slots_from_other_shards() {
+        let registry = DynArcRegistry::new();
+        let keys: Vec<_> = (0..SHARD_COUNT)
+            .map(|i| registry.acquire_and_init(|| i))
+            .collect();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for &key in &keys {
+                    unsafe { registry.dec_count(key) };
+                }
+            });
+        });
+
+        for i in 0..SHARD_COUNT {
+            registry.acquire_and_init(|| i);
+        }
+        assert_eq!(registry.live_count(), SHARD_COUNT);
+        assert_eq!(registry.len.load(Ordering::Relaxed), SHARD_COUNT);
+    }
+
+    #[test]
+    fn init_panic_frees_slot() {
+        let registry = DynArcRegistry::<i64>::new();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            registry.acquire_and_init(|| panic!("init failed"))
+        }));
+        assert!(res.is_err());
+
+        assert_eq!(registry.live_count(), 0);
+        let key = registry.acquire_and_init(|| 1);
+        assert_eq!(key.index, 0);
+    }
+
+    #[test]
+    fn drops_alive_items() {
+        let item = Rc::new(());
+        {
+            let registry = DynArcRegistry::new();
+            registry.acquire_and_init(|| item.clone());
+            assert_eq!(Rc::strong_count(&item), 2);
+        }
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    fn roundtrip<R: ArcRegistry<i64>>(registry: &R) {
+        let index = registry.acquire_and_init(|| 7).unwrap();
+        assert!(registry.try_inc_count(index));
+        assert_eq!(unsafe { *registry.get_ref(index) }, 7);
+        unsafe {
+            registry.dec_count(index);
+            registry.dec_count(index);
+        }
+        assert_eq!(registry.live_count(), 0);
+    }
+
+    #[test]
+    fn shared_api() {
+        roundtrip(&UnsafeArcArray::<1, i64>::new());
+        roundtrip(&DynArcRegistry::<i64>::new());
+    }
+}
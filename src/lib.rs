@@ -1,2 +1,5 @@
+#[cfg(test)]
+mod alloc_counter;
 pub mod arc_array;
+pub mod dyn_arc_registry;
 pub mod vk;
@@ -35,7 +35,7 @@ fn main() {
 
     let devices = physical_device::enumerate(&instance);
     let graphic_families = devices[0]
-        .get_available_queue_families()
+        .get_available_queues()
         .into_iter()
         .enumerate()
         .filter(|(_idx, qf)| qf.has_graphics())
@@ -43,9 +43,12 @@ fn main() {
 
     log::info!("{:?}", graphic_families);
 
-    assert!(graphic_families.len() > 0);
+    assert!(!graphic_families.is_empty());
     assert!(graphic_families[0].1.has_graphics());
     assert!(graphic_families[0].1.queue_count() > 0);
     assert!(graphic_families[0].1.belongs_to_device(&devices[0]));
-    assert_eq!(graphic_families[0].1.get_idx(), graphic_families[0].0);
+    assert_eq!(
+        graphic_families[0].1.get_family_idx(),
+        graphic_families[0].0
+    );
 }
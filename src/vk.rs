@@ -1,10 +1,152 @@
+//!
+//! Vulkan wrappers
+//!
+//! Thread safety: wrappers around objects that Vulkan allows to use from several threads without
+//! external synchronization (Instance, PhysicalDevice and the info structs obtained from them) are
+//! Send + Sync. Wrappers around externally synchronized objects (e.g. command pools) must be
+//! !Sync, so the borrow checker enforces the synchronization instead. Each wrapper asserts its
+//! markers at compile time with assert_send_sync
+//!
+//! Errors: every function that fails on a vulkan error code comes in two variants. The try_*
+//! variant returns a VkError, the one without the prefix logs and panics with the same message.
+//! Queries that also need a minimum Vulkan version return UnsupportedVersion without the prefix
+//! and DeviceQueryError with it, so only vulkan errors panic
+//!
+
+use std::{ffi::CStr, fmt};
+
 pub mod entry;
 pub mod error;
 pub mod extension;
 pub mod instance;
 pub mod physical_device;
+pub mod queue_graph;
+pub mod raw_handle;
 pub mod validation_layer;
+pub mod version;
 
 pub use extension::Extension;
 pub use instance::Instance;
 pub use validation_layer::ValidationLayer;
+pub use version::ApiVersion;
+
+/// Log targets of the crate's subsystems. Verbosity can be raised for a single subsystem, e.g.
+/// `RUST_LOG=pibaf::instance=trace`. Each message starts with a stable ID in brackets, so it can
+/// be matched regardless of its wording
+pub mod log_target {
+    /// Loading the vulkan entry
+    pub const ENTRY: &str = "pibaf::entry";
+    /// Instance creation and destruction, extension and layer enumeration
+    pub const INSTANCE: &str = "pibaf::instance";
+    /// Physical device enumeration and queries
+    pub const DEVICE: &str = "pibaf::device";
+    /// Fatal vulkan errors
+    pub const ERROR: &str = "pibaf::error";
+}
+
+/// Formats a raw handle value for logs and Debug output. With the redact-handles feature the
+/// value is replaced by a hash keyed per process, so logs collected from users do not leak
+/// addresses, but the same handle is still recognizable within one log
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LoggedHandle(pub u64);
+
+impl LoggedHandle {
+    pub fn new(handle: impl ash::vk::Handle) -> Self {
+        Self(handle.as_raw())
+    }
+}
+
+impl fmt::Display for LoggedHandle {
+    #[cfg(not(feature = "redact-handles"))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+
+    #[cfg(feature = "redact-handles")]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use std::{
+            hash::{BuildHasher, RandomState},
+            sync::LazyLock,
+        };
+
+        static KEY: LazyLock<RandomState> = LazyLock::new(RandomState::new);
+        // 0 stays recognizable as a null handle
+        if self.0 == 0 {
+            return write!(f, "0x0");
+        }
+        write!(f, "#{:016x}", KEY.hash_one(self.0))
+    }
+}
+
+impl fmt::Debug for LoggedHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Fails to compile if T is not Send + Sync
+pub(crate) const fn assert_send_sync<T: Send + Sync>() {}
+
+/// Returns the indices of names in ascending order, so the names can be binary searched with
+/// find_name. Evaluated at compile time for the extension and layer name tables
+pub(crate) const fn sort_names<const N: usize>(names: &[&CStr; N]) -> [usize; N] {
+    const fn less(a: &CStr, b: &CStr) -> bool {
+        let (a, b) = (a.to_bytes(), b.to_bytes());
+        let mut i = 0;
+        while i < a.len() && i < b.len() {
+            if a[i] != b[i] {
+                return a[i] < b[i];
+            }
+            i += 1;
+        }
+        a.len() < b.len()
+    }
+
+    let mut sorted = [0; N];
+    let mut i = 0;
+    while i < N {
+        sorted[i] = i;
+        i += 1;
+    }
+    // Insertion sort, the tables are small
+    let mut i = 1;
+    while i < N {
+        let mut j = i;
+        while j > 0 && less(names[sorted[j]], names[sorted[j - 1]]) {
+            let tmp = sorted[j];
+            sorted[j] = sorted[j - 1];
+            sorted[j - 1] = tmp;
+            j -= 1;
+        }
+        i += 1;
+    }
+    sorted
+}
+
+/// Returns the index of the given name in names, using the order produced by sort_names
+pub(crate) fn find_name(names: &[&CStr], sorted: &[usize], name: &CStr) -> Option<usize> {
+    sorted
+        .binary_search_by(|&i| names[i].cmp(name))
+        .ok()
+        .map(|pos| sorted[pos])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn logged_handle() {
+        let handle = LoggedHandle(0xdead_beef);
+        // Stays correlatable
+        assert_eq!(handle.to_string(), LoggedHandle(0xdead_beef).to_string());
+        assert_ne!(handle.to_string(), LoggedHandle(0xbeef).to_string());
+        assert_eq!(LoggedHandle(0).to_string(), "0x0");
+
+        if cfg!(feature = "redact-handles") {
+            assert!(!handle.to_string().contains("deadbeef"));
+        } else {
+            assert_eq!(handle.to_string(), "0xdeadbeef");
+        }
+    }
+}
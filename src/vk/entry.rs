@@ -1,8 +1,64 @@
-use std::sync::LazyLock;
+use std::{ffi::CStr, sync::LazyLock};
+
+use super::{
+    ApiVersion,
+    error::{VkError, check_vk_success},
+    extension::{self, AvailableExtension},
+    log_target,
+};
 
 pub(in crate::vk) static ENTRY: LazyLock<ash::Entry> = LazyLock::new(|| {
     // Safety: Entry::load() cannot actually cause UB
     let entry = unsafe { ash::Entry::load() }.expect("vulkan is not suppoted");
-    log::info!("Loaded entry");
+    log::info!(target: log_target::ENTRY, "[entry.loaded] Loaded entry");
     entry
 });
+
+/// Highest instance API version supported by the loader, without creating an instance
+pub fn instance_version() -> ApiVersion {
+    try_instance_version().unwrap_or_else(|e| e.fatal())
+}
+
+/// Highest instance API version supported by the loader, without creating an instance
+pub fn try_instance_version() -> Result<ApiVersion, VkError> {
+    // Safety: ENTRY is never destroyed
+    let version = check_vk_success("Failed to enumerate instance version", unsafe {
+        ENTRY.try_enumerate_instance_version()
+    })?;
+    // vkEnumerateInstanceVersion only exists since Vulkan 1.1
+    Ok(version.map_or(ApiVersion::V1_0, ApiVersion::from_raw))
+}
+
+/// Enumerates the instance extensions provided by the layer with the given name
+/// # Panics
+/// Panics if the layer is not available
+pub fn enumerate_layer_extensions(layer_name: &CStr) -> Vec<AvailableExtension> {
+    try_enumerate_layer_extensions(layer_name).unwrap_or_else(|e| e.fatal())
+}
+
+/// Enumerates the instance extensions provided by the layer with the given name. Returns an
+/// error if the layer is not available
+pub fn try_enumerate_layer_extensions(
+    layer_name: &CStr,
+) -> Result<Vec<AvailableExtension>, VkError> {
+    extension::enumerate_provided_by(Some(layer_name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn version() {
+        assert!(instance_version() >= ApiVersion::V1_0);
+    }
+
+    #[test]
+    fn missing_layer() {
+        let error = try_enumerate_layer_extensions(c"VK_LAYER_PIBAF_missing").unwrap_err();
+        assert_eq!(
+            error.result(),
+            crate::vk::error::VulkanResult::ErrorLayerNotPresent
+        );
+    }
+}
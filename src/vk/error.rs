@@ -1,8 +1,16 @@
-use ash::vk;
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    fmt,
+};
 
-/// Enumeration of all possible VkResult codes
+use ash::vk::{self, Handle};
+
+use super::{LoggedHandle, log_target};
+
+/// Enumeration of the core VkResult codes. Codes added by extensions or unknown to this crate are
+/// kept as Unknown
 #[cfg_attr(test, derive(strum::EnumIter))] // for testing all enum variants
-#[derive(PartialEq, Debug, strum::FromRepr, strum::Display)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, strum::FromRepr, strum::Display)]
 #[repr(i32)]
 pub enum VulkanResult {
     #[doc = "Command completed successfully"]
@@ -62,6 +70,9 @@ pub enum VulkanResult {
     #[doc = "An unknown error has occurred, due to an implementation or application bug"]
     #[strum(to_string = "ERROR_UNKNOWN")]
     ErrorUnknown = -13,
+    #[doc = "A result code this crate does not know about, for example one added by an extension"]
+    #[strum(to_string = "VK_RESULT({0})")]
+    Unknown(i32) = i32::MIN,
 }
 
 impl VulkanResult {
@@ -92,19 +103,151 @@ impl VulkanResult {
             Self::ErrorUnknown => {
                 "An unknown error has occurred, due to an implementation or application bug"
             }
+            Self::Unknown(_) => "A result code this crate does not know about",
         }
     }
 }
 
 impl From<vk::Result> for VulkanResult {
     fn from(value: vk::Result) -> Self {
-        Self::from_repr(value.as_raw()).expect("all VkResult cases are covered")
+        let raw = value.as_raw();
+        match Self::from_repr(raw) {
+            Some(Self::Unknown(_)) | None => Self::Unknown(raw),
+            Some(result) => result,
+        }
+    }
+}
+
+/// A vulkan object involved in a failed call
+#[derive(Clone, PartialEq, Eq)]
+pub struct ErrorObject {
+    pub object_type: vk::ObjectType,
+    pub handle: u64,
+    /// Debug name of the object, if it has one
+    pub name: Option<String>,
+}
+
+/// Formats the handle through LoggedHandle, so it is redacted with the redact-handles feature
+impl fmt::Debug for ErrorObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorObject")
+            .field("object_type", &self.object_type)
+            .field("handle", &LoggedHandle(self.handle))
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl fmt::Display for ErrorObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {}", self.object_type, LoggedHandle(self.handle))?;
+        if let Some(name) = &self.name {
+            write!(f, " \"{name}\"")?;
+        }
+        Ok(())
+    }
+}
+
+/// A failed vulkan call. Returned by the try_* functions, while their counterparts without the
+/// prefix panic with the same message
+#[derive(Debug)]
+pub struct VkError {
+    context: &'static str,
+    result: VulkanResult,
+    objects: Vec<ErrorObject>,
+    /// Captured in debug builds only, if enabled by RUST_BACKTRACE
+    backtrace: Option<Backtrace>,
+}
+
+impl VkError {
+    pub fn new<T: Into<VulkanResult>>(context: &'static str, result: T) -> Self {
+        Self {
+            context,
+            result: result.into(),
+            objects: Vec::new(),
+            backtrace: cfg!(debug_assertions).then(Backtrace::capture),
+        }
+    }
+
+    /// Adds an object involved in the failed call
+    pub fn with_object<H: Handle>(mut self, handle: H, name: Option<&str>) -> Self {
+        self.objects.push(ErrorObject {
+            object_type: H::TYPE,
+            handle: handle.as_raw(),
+            name: name.map(str::to_owned),
+        });
+        self
+    }
+
+    /// Description of the failed operation
+    pub fn context(&self) -> &'static str {
+        self.context
+    }
+
+    /// Result code returned by the failed call
+    pub fn result(&self) -> VulkanResult {
+        self.result
+    }
+
+    /// Objects involved in the failed call
+    pub fn objects(&self) -> &[ErrorObject] {
+        &self.objects
+    }
+
+    /// Backtrace of the failed call. None in release builds or if capturing is disabled
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace
+            .as_ref()
+            .filter(|backtrace| backtrace.status() == BacktraceStatus::Captured)
+    }
+
+    /// Logs and panics with the error, like fatal_vk_error
+    pub fn fatal(self) -> ! {
+        log::error!(target: log_target::ERROR, "[error.fatal] fatal: {self}");
+        panic!("fatal: {self}");
     }
 }
 
+/// The first line matches fatal_vk_error, the involved objects and the backtrace follow on
+/// separate lines
+impl fmt::Display for VkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ({})",
+            self.context,
+            self.result,
+            self.result.doc()
+        )?;
+        for object in &self.objects {
+            write!(f, "\n  involving {object}")?;
+        }
+        if let Some(backtrace) = self.backtrace() {
+            write!(f, "\nbacktrace:\n{backtrace}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VkError {}
+
+/// Converts the result of a vulkan call into a VkError with the given context
+pub fn check_vk_success<T>(
+    context: &'static str,
+    res: Result<T, vk::Result>,
+) -> Result<T, VkError> {
+    res.map_err(|e| VkError::new(context, e))
+}
+
 pub fn fatal_vk_error<T: Into<VulkanResult>>(msg: &str, error: T) -> ! {
     let e = error.into();
-    log::error!("fatal: {}: {} ({})", msg, e, e.doc());
+    log::error!(
+        target: log_target::ERROR,
+        "[error.fatal] fatal: {}: {} ({})",
+        msg,
+        e,
+        e.doc()
+    );
     panic!("fatal: {}: {} ({})", msg, e, e.doc());
 }
 
@@ -128,6 +271,16 @@ mod test {
         assert_eq!(vulkan_result, VulkanResult::ErrorUnknown);
     }
 
+    #[test]
+    fn from_extension_vk_result() {
+        let result = VulkanResult::from(vk::Result::ERROR_SURFACE_LOST_KHR);
+        assert_eq!(result, VulkanResult::Unknown(-1000000000));
+        assert_eq!(result.to_string(), "VK_RESULT(-1000000000)");
+
+        let result = VulkanResult::from(vk::Result::from_raw(i32::MIN));
+        assert_eq!(result, VulkanResult::Unknown(i32::MIN));
+    }
+
     #[test]
     fn name() {
         let vulkan_result = VulkanResult::from(vk::Result::from_raw(-13));
@@ -171,6 +324,59 @@ mod test {
         expect_vk_success("ohno", result)
     }
 
+    #[test]
+    fn check_err_vk_result() {
+        let result: Result<(), _> = Err(vk::Result::from_raw(-13));
+        let error = check_vk_success("ohno", result).unwrap_err();
+        assert_eq!(error.context(), "ohno");
+        assert_eq!(error.result(), VulkanResult::ErrorUnknown);
+        // The backtrace may follow on the next lines
+        assert_eq!(
+            error.to_string().lines().next().unwrap(),
+            "ohno: ERROR_UNKNOWN (An unknown error has occurred, due to an implementation or application bug)"
+        );
+    }
+
+    #[test]
+    fn error_objects() {
+        let error = VkError::new("ohno", vk::Result::from_raw(-13))
+            .with_object(vk::Buffer::from_raw(0x1f), Some("vertices"))
+            .with_object(vk::Pipeline::from_raw(0x2a), None);
+        assert_eq!(error.objects().len(), 2);
+        assert_eq!(error.objects()[0].object_type, vk::ObjectType::BUFFER);
+
+        let message = error.to_string();
+        let mut lines = message.lines();
+        assert!(lines.next().unwrap().starts_with("ohno: ERROR_UNKNOWN"));
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("  involving BUFFER {} \"vertices\"", LoggedHandle(0x1f))
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("  involving PIPELINE {}", LoggedHandle(0x2a))
+        );
+    }
+
+    #[test]
+    fn error_objects_debug() {
+        let error = VkError::new("ohno", vk::Result::from_raw(-13))
+            .with_object(vk::Buffer::from_raw(0xdead_beef), Some("vertices"));
+        let debug = format!("{error:?}");
+        assert!(debug.contains(&format!("handle: {}", LoggedHandle(0xdead_beef))));
+
+        if cfg!(feature = "redact-handles") {
+            assert!(!debug.contains("deadbeef"));
+            assert!(!debug.contains(&0xdead_beef_u64.to_string()));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "fatal: ohno: ERROR_UNKNOWN")]
+    fn error_fatal() {
+        VkError::new("ohno", vk::Result::from_raw(-13)).fatal()
+    }
+
     #[test]
     fn expect_ok_vk_result() {
         let result = Ok(11);
@@ -1,20 +1,44 @@
-use std::ffi::{CStr, CString};
+use std::{
+    borrow::Cow,
+    ffi::{CStr, CString},
+};
 
-use strum::{EnumCount, IntoEnumIterator};
+use ash::vk;
+use strum::EnumCount;
 
-use super::{entry, error::expect_vk_success};
+use super::{
+    assert_send_sync, entry,
+    error::{VkError, check_vk_success},
+    find_name, log_target, sort_names,
+    validation_layer::AvailableValidationLayers,
+};
 
 const EXTENSION_NAMES: [&CStr; Extension::COUNT] = [
     c"VK_KHR_surface",
+    c"VK_KHR_get_physical_device_properties2",
+    c"VK_EXT_debug_utils",
+    c"VK_KHR_portability_enumeration",
+    c"VK_KHR_win32_surface",
+    c"VK_KHR_wayland_surface",
+    c"VK_KHR_xcb_surface",
+    c"VK_EXT_metal_surface",
     c"__UNKNOWN_EXTENSION",
     c"__UNREACHABLE_EXTENSION",
 ];
+const SORTED_EXTENSION_NAMES: [usize; Extension::COUNT] = sort_names(&EXTENSION_NAMES);
 
 /// Enumeration of all supported extensions, plus UnknownExtension and UnreachableExtension
-#[derive(Clone, Copy, strum::EnumCount, strum::EnumIter, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, strum::EnumCount, strum::EnumIter, strum::FromRepr, PartialEq, Eq, Debug)]
 #[repr(usize)]
 pub enum Extension {
     KhrSurface,
+    KhrGetPhysicalDeviceProperties2,
+    ExtDebugUtils,
+    KhrPortabilityEnumeration,
+    KhrWin32Surface,
+    KhrWaylandSurface,
+    KhrXcbSurface,
+    ExtMetalSurface,
     UnknownExtension,
     UnreachableExtension,
 }
@@ -29,8 +53,8 @@ impl Extension {
 
     /// Return the first enum variant with name mathing the given string. Returns UnknownExtension if the name doesn't match any variant
     pub fn identify_name(name: &CStr) -> Self {
-        Self::iter()
-            .find(|extension| extension.name() == name)
+        find_name(&EXTENSION_NAMES, &SORTED_EXTENSION_NAMES, name)
+            .and_then(Self::from_repr)
             .unwrap_or(Self::UnknownExtension)
     }
 }
@@ -40,11 +64,40 @@ impl Extension {
 #[derive(Clone, Debug)]
 pub struct AvailableExtension {
     extension: Extension,
-    name: CString,
+    /// Borrows the static name of known extensions, only unknown names are allocated
+    name: Cow<'static, CStr>,
     spec_version: u32,
+    /// Name of the layer providing the extension, None if the implementation provides it
+    layer: Option<CString>,
 }
 
 impl AvailableExtension {
+    /// None if the name is not NUL terminated. Such extensions can not be enabled, so they are
+    /// skipped with a warning
+    fn from_properties(prop: &vk::ExtensionProperties, layer: Option<&CStr>) -> Option<Self> {
+        let raw_name = match prop.extension_name_as_c_str() {
+            Ok(name) => name,
+            Err(e) => {
+                log::warn!(
+                    target: log_target::INSTANCE,
+                    "[instance.invalid_name] Skipped an enumerated extension with an invalid name: {e}"
+                );
+                return None;
+            }
+        };
+        let extension = Extension::identify_name(raw_name);
+        let name = match extension {
+            Extension::UnknownExtension => Cow::Owned(CString::from(raw_name)),
+            _ => Cow::Borrowed(extension.name()),
+        };
+        Some(Self {
+            extension,
+            name,
+            spec_version: prop.spec_version,
+            layer: layer.map(CStr::to_owned),
+        })
+    }
+
     /// Returns the extension variant
     pub fn extension(&self) -> Extension {
         self.extension
@@ -58,31 +111,78 @@ impl AvailableExtension {
     pub fn spec_version(&self) -> u32 {
         self.spec_version
     }
+
+    /// Name of the layer providing the extension. Such extensions are only available if the
+    /// layer is enabled too. None if the implementation provides the extension
+    pub fn provided_by_layer(&self) -> Option<&CStr> {
+        self.layer.as_deref()
+    }
 }
-/// Enumerates available instance extensions. Ignores unkwown names.
+/// Enumerates available instance extensions. Extensions with unknown names are kept as
+/// UnknownExtension along with their driver name, so they can still be enabled with add_by_name
 pub fn enumerate() -> Vec<AvailableExtension> {
+    try_enumerate().unwrap_or_else(|e| e.fatal())
+}
+
+/// Enumerates available instance extensions. Extensions with unknown names are kept as
+/// UnknownExtension along with their driver name, so they can still be enabled with add_by_name
+pub fn try_enumerate() -> Result<Vec<AvailableExtension>, VkError> {
+    enumerate_provided_by(None)
+}
+
+/// Enumerates available instance extensions, including the ones provided by the given layers.
+/// Extensions provided by the implementation are preferred over the layer-provided ones
+pub fn enumerate_with_layers(layers: &AvailableValidationLayers) -> Vec<AvailableExtension> {
+    try_enumerate_with_layers(layers).unwrap_or_else(|e| e.fatal())
+}
+
+/// Enumerates available instance extensions, including the ones provided by the given layers.
+/// Extensions provided by the implementation are preferred over the layer-provided ones
+pub fn try_enumerate_with_layers(
+    layers: &AvailableValidationLayers,
+) -> Result<Vec<AvailableExtension>, VkError> {
+    let mut extensions = try_enumerate()?;
+    for layer in layers.layers() {
+        let layer_extensions = enumerate_provided_by(Some(layer.name()))?;
+        merge_layer_extensions(&mut extensions, layer_extensions);
+    }
+    Ok(extensions)
+}
+
+/// Appends the layer-provided extensions that are not in extensions yet
+fn merge_layer_extensions(
+    extensions: &mut Vec<AvailableExtension>,
+    layer_extensions: Vec<AvailableExtension>,
+) {
+    for extension in layer_extensions {
+        if !extensions
+            .iter()
+            .any(|known| known.name() == extension.name())
+        {
+            extensions.push(extension);
+        }
+    }
+}
+
+/// Enumerates the extensions provided by the given layer, or by the implementation and the
+/// implicitly enabled layers if layer is None
+pub(in crate::vk) fn enumerate_provided_by(
+    layer: Option<&CStr>,
+) -> Result<Vec<AvailableExtension>, VkError> {
     // Safety: ENTRY is never destroyed
-    let extensions = expect_vk_success("Failed to enumerate extensions", unsafe {
-        entry::ENTRY.enumerate_instance_extension_properties(None)
-    });
+    let extensions = check_vk_success("Failed to enumerate extensions", unsafe {
+        entry::ENTRY.enumerate_instance_extension_properties(layer)
+    })?;
 
     let extensions = extensions
-        .into_iter()
-        .flat_map(|prop| {
-            let name = prop
-                .extension_name_as_c_str()
-                .expect("Got invalid extension name from enumeration")
-                .to_owned();
-            let extension = Extension::identify_name(&name);
-            Some(AvailableExtension {
-                extension,
-                name,
-                spec_version: prop.spec_version,
-            })
-        })
+        .iter()
+        .filter_map(|prop| AvailableExtension::from_properties(prop, layer))
         .collect();
-    log::trace!("Enumerated extensions, avalilable extensions: {extensions:#?}");
-    extensions
+    log::trace!(
+        target: log_target::INSTANCE,
+        "[instance.extensions] Enumerated extensions, avalilable extensions: {extensions:#?}"
+    );
+    Ok(extensions)
 }
 
 /// List of some of the available extensions. Guarantees avalilability. Used to safely
@@ -93,12 +193,9 @@ pub struct AvailableExtensions {
 }
 
 impl AvailableExtensions {
-    /// Returns Vec of contained extensions' names
-    pub fn names(&self) -> Vec<&CStr> {
-        self.extensions
-            .iter()
-            .map(|extension| extension.name())
-            .collect()
+    /// Iterates over the contained extensions' names without allocating
+    pub fn names(&self) -> impl ExactSizeIterator<Item = &CStr> {
+        self.extensions.iter().map(|extension| extension.name())
     }
 
     /// Slice of avalilable extensions
@@ -111,8 +208,19 @@ impl AvailableExtensions {
         self.extensions.push(extension);
     }
 
+    /// Adds the extension from available with the given driver name. Works for extensions unknown
+    /// to this crate too. Returns false if no such extension is available
+    pub fn add_by_name(&mut self, available: &[AvailableExtension], name: &CStr) -> bool {
+        let Some(extension) = available.iter().find(|avail| avail.name() == name) else {
+            return false;
+        };
+        self.add(extension.clone());
+        true
+    }
+
     /// If avalilable contains each element from required, returns Self containing all required
-    /// extensions, else returns None
+    /// extensions, else returns None. Pass the list from enumerate_with_layers to also accept
+    /// extensions provided by the layers that will be enabled
     pub fn from_available_and_required(
         available: &[AvailableExtension],
         required: &[Extension],
@@ -129,9 +237,14 @@ impl AvailableExtensions {
     }
 }
 
+const _: () = assert_send_sync::<AvailableExtension>();
+const _: () = assert_send_sync::<AvailableExtensions>();
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::vk::instance::{InstanceCreateInfo, InstanceCreateInfoError};
+
     #[test]
     fn name() {
         let extension = Extension::KhrSurface;
@@ -144,12 +257,143 @@ mod test {
         assert_eq!(extension, Extension::KhrSurface);
     }
 
+    #[test]
+    fn identify_all() {
+        use strum::IntoEnumIterator;
+
+        for extension in Extension::iter() {
+            assert_eq!(Extension::identify_name(extension.name()), extension);
+        }
+        assert!(SORTED_EXTENSION_NAMES.is_sorted_by_key(|&i| EXTENSION_NAMES[i]));
+    }
+
     #[test]
     fn identify_not_found() {
         let extension = Extension::identify_name(c"garbage");
         assert_eq!(extension, Extension::UnknownExtension);
     }
 
+    #[test]
+    fn add_unknown_by_name() {
+        let available = [AvailableExtension {
+            extension: Extension::UnknownExtension,
+            name: Cow::Owned(c"VK_VENDOR_internal".to_owned()),
+            spec_version: 3,
+            layer: None,
+        }];
+
+        let mut res = AvailableExtensions::default();
+        assert!(!res.add_by_name(&available, c"VK_VENDOR_missing"));
+        assert!(res.add_by_name(&available, c"VK_VENDOR_internal"));
+
+        assert!(res.names().eq([c"VK_VENDOR_internal"]));
+        assert_eq!(res.extensions()[0].extension(), Extension::UnknownExtension);
+        assert_eq!(res.extensions()[0].spec_version(), 3);
+    }
+
+    #[test]
+    fn names_do_not_allocate() {
+        use crate::alloc_counter::allocations_during;
+
+        // Up to 8 name pointers are stored inline by RawInstanceCreateInfo
+        let mut extensions = AvailableExtensions::default();
+        for extension in [
+            Extension::KhrSurface,
+            Extension::KhrGetPhysicalDeviceProperties2,
+            Extension::ExtDebugUtils,
+            Extension::KhrWin32Surface,
+            Extension::KhrWaylandSurface,
+            Extension::KhrXcbSurface,
+            Extension::ExtMetalSurface,
+        ] {
+            extensions.add(AvailableExtension {
+                extension,
+                name: Cow::Borrowed(extension.name()),
+                spec_version: 1,
+                layer: None,
+            });
+        }
+        extensions.add(AvailableExtension {
+            extension: Extension::UnknownExtension,
+            name: Cow::Owned(c"VK_VENDOR_internal".to_owned()),
+            spec_version: 1,
+            layer: None,
+        });
+
+        let (count, allocations) = allocations_during(|| extensions.names().count());
+        assert_eq!(count, 8);
+        assert_eq!(allocations, 0);
+
+        let info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .extensions(extensions)
+            .build()
+            .unwrap();
+        let (enabled, allocations) = allocations_during(|| {
+            info.create_raw()
+                .vk_instance_create_info()
+                .enabled_extension_count
+        });
+        assert_eq!(enabled, 8);
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn skip_invalid_name() {
+        let mut prop = vk::ExtensionProperties::default();
+        prop.extension_name.fill(b'a' as _);
+        assert!(AvailableExtension::from_properties(&prop, None).is_none());
+
+        let prop = vk::ExtensionProperties::default()
+            .extension_name(c"VK_KHR_surface")
+            .unwrap();
+        let extension = AvailableExtension::from_properties(&prop, None).unwrap();
+        assert_eq!(extension.extension(), Extension::KhrSurface);
+    }
+
+    #[test]
+    fn merge_layer_provided() {
+        let known = |extension: Extension, layer: Option<&CStr>| AvailableExtension {
+            extension,
+            name: Cow::Borrowed(extension.name()),
+            spec_version: 1,
+            layer: layer.map(CStr::to_owned),
+        };
+        let mut extensions = vec![known(Extension::KhrSurface, None)];
+        merge_layer_extensions(
+            &mut extensions,
+            vec![
+                known(Extension::KhrSurface, Some(c"VK_LAYER_a")),
+                known(
+                    Extension::KhrGetPhysicalDeviceProperties2,
+                    Some(c"VK_LAYER_a"),
+                ),
+            ],
+        );
+
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions[0].provided_by_layer(), None);
+        assert_eq!(extensions[1].provided_by_layer(), Some(c"VK_LAYER_a"));
+
+        let res = AvailableExtensions::from_available_and_required(
+            &extensions,
+            &[Extension::KhrGetPhysicalDeviceProperties2],
+        );
+        assert!(res.is_some());
+
+        // The extension can not be enabled without its layer
+        let info = InstanceCreateInfo::builder()
+            .extensions(res.unwrap())
+            .api_version(vk::API_VERSION_1_0)
+            .build();
+        assert!(matches!(
+            info,
+            Err(InstanceCreateInfoError::LayerNotEnabled { extension, layer })
+                if extension.as_c_str() == Extension::KhrGetPhysicalDeviceProperties2.name()
+                    && layer.as_c_str() == c"VK_LAYER_a"
+        ));
+    }
+
     #[test]
     fn has_khronos() {
         let available = enumerate();
@@ -174,7 +418,7 @@ mod test {
         let mut res = AvailableExtensions::default();
         res.add(khr);
 
-        assert_eq!(res.names(), &[c"VK_KHR_surface"]);
+        assert!(res.names().eq([c"VK_KHR_surface"]));
     }
 
     #[test]
@@ -194,6 +438,6 @@ mod test {
 
         let res = AvailableExtensions::from_available_and_required(&available, &required).unwrap();
 
-        assert_eq!(&res.names(), &[c"VK_KHR_surface"]);
+        assert!(res.names().eq([c"VK_KHR_surface"]));
     }
 }
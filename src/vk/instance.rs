@@ -1,31 +1,68 @@
 use std::{
     ffi::{CStr, CString, NulError, c_char},
+    fmt::Debug,
     ops::Deref,
+    path::Path,
+    ptr,
+    sync::OnceLock,
 };
 
-use ash::{
-    self,
-    vk::{self, Handle},
-};
+use ash::{self, vk};
+use smallvec::SmallVec;
+
+pub mod fallback;
 
 use crate::{
     arc_array::UnsafeArcArray,
     vk::{
-        entry, error::expect_vk_success, extension::AvailableExtensions,
-        validation_layer::AvailableValidationLayers,
+        ApiVersion, Extension, LoggedHandle, assert_send_sync, entry,
+        error::{VkError, VulkanResult, check_vk_success},
+        extension::AvailableExtensions,
+        log_target,
+        raw_handle::RawInstanceHandle,
+        validation_layer::{AvailableValidationLayers, ValidationLayer},
     },
 };
 
-/// ash::Instance wrapper that destroys the Instance when dropped
-pub struct RawInstance(ash::Instance);
+/// ash::Instance wrapper that destroys the Instance when dropped, unless it is owned externally
+pub struct RawInstance {
+    instance: ash::Instance,
+    /// Imported with Instance::from_raw_external, destroyed by its owner instead
+    external: bool,
+    api_version: ApiVersion,
+    enabled_extensions: Vec<Extension>,
+
+    // Extension function tables, loaded once instead of at every call site
+    surface_fns: Option<ash::khr::surface::Instance>,
+    #[cfg(feature = "surface-win32")]
+    win32_surface_fns: Option<ash::khr::win32_surface::Instance>,
+    #[cfg(feature = "surface-wayland")]
+    wayland_surface_fns: Option<ash::khr::wayland_surface::Instance>,
+    #[cfg(feature = "surface-xcb")]
+    xcb_surface_fns: Option<ash::khr::xcb_surface::Instance>,
+    #[cfg(feature = "surface-metal")]
+    metal_surface_fns: Option<ash::ext::metal_surface::Instance>,
+    properties2_fns: Option<ash::khr::get_physical_device_properties2::Instance>,
+    cooperative_matrix_fns: OnceLock<ash::khr::cooperative_matrix::Instance>,
+}
 
 impl Drop for RawInstance {
     fn drop(&mut self) {
-        let handle = self.0.handle().as_raw();
+        let handle = LoggedHandle::new(self.instance.handle());
+        if self.external {
+            log::info!(
+                target: log_target::INSTANCE,
+                "[instance.released] Released external instance: {handle}"
+            );
+            return;
+        }
         unsafe {
-            self.0.destroy_instance(None);
+            self.instance.destroy_instance(None);
         }
-        log::info!("Destroyed instance: {handle}");
+        log::info!(
+            target: log_target::INSTANCE,
+            "[instance.destroyed] Destroyed instance: {handle}"
+        );
     }
 }
 
@@ -33,7 +70,71 @@ impl RawInstance {
     /// # Safety
     /// The ash::Instance should not be destroyed
     pub unsafe fn get_raw_ref(&self) -> &ash::Instance {
-        &self.0
+        &self.instance
+    }
+
+    /// Raw handle for passing the instance to other Vulkan libraries. The handle must not be
+    /// destroyed while this instance is alive
+    pub fn as_raw(&self) -> RawInstanceHandle {
+        RawInstanceHandle::from_raw(self.instance.handle())
+    }
+
+    /// Checks if the instance was imported with Instance::from_raw_external and will not be
+    /// destroyed on drop
+    pub fn is_external(&self) -> bool {
+        self.external
+    }
+
+    /// The API version the instance was created with
+    pub fn api_version(&self) -> ApiVersion {
+        self.api_version
+    }
+
+    /// Checks if the extension was enabled when creating the instance
+    pub fn has_extension(&self, extension: Extension) -> bool {
+        self.enabled_extensions.contains(&extension)
+    }
+
+    /// VK_KHR_surface functions. None if the extension is not enabled
+    pub fn surface_fns(&self) -> Option<&ash::khr::surface::Instance> {
+        self.surface_fns.as_ref()
+    }
+
+    /// VK_KHR_win32_surface functions. None if the extension is not enabled
+    #[cfg(feature = "surface-win32")]
+    pub fn win32_surface_fns(&self) -> Option<&ash::khr::win32_surface::Instance> {
+        self.win32_surface_fns.as_ref()
+    }
+
+    /// VK_KHR_wayland_surface functions. None if the extension is not enabled
+    #[cfg(feature = "surface-wayland")]
+    pub fn wayland_surface_fns(&self) -> Option<&ash::khr::wayland_surface::Instance> {
+        self.wayland_surface_fns.as_ref()
+    }
+
+    /// VK_KHR_xcb_surface functions. None if the extension is not enabled
+    #[cfg(feature = "surface-xcb")]
+    pub fn xcb_surface_fns(&self) -> Option<&ash::khr::xcb_surface::Instance> {
+        self.xcb_surface_fns.as_ref()
+    }
+
+    /// VK_EXT_metal_surface functions. None if the extension is not enabled
+    #[cfg(feature = "surface-metal")]
+    pub fn metal_surface_fns(&self) -> Option<&ash::ext::metal_surface::Instance> {
+        self.metal_surface_fns.as_ref()
+    }
+
+    /// VK_KHR_get_physical_device_properties2 functions. None if the extension is not enabled
+    pub fn properties2_fns(&self) -> Option<&ash::khr::get_physical_device_properties2::Instance> {
+        self.properties2_fns.as_ref()
+    }
+
+    /// VK_KHR_cooperative_matrix functions, loaded on the first call. Being a device extension,
+    /// it must be supported by the physical device the functions are called with
+    pub fn cooperative_matrix_fns(&self) -> &ash::khr::cooperative_matrix::Instance {
+        self.cooperative_matrix_fns.get_or_init(|| {
+            ash::khr::cooperative_matrix::Instance::new(&entry::ENTRY, &self.instance)
+        })
     }
 }
 #[cfg(not(test))]
@@ -41,14 +142,61 @@ const MAX_INSTANCES: usize = 1; // One instance is usually enough
 #[cfg(test)]
 const MAX_INSTANCES: usize = 10; // Tests can run simultaniously and can store more that 1 instance
 // ath a time
+#[cfg(not(loom))]
 static RAW_INSTANCES: UnsafeArcArray<MAX_INSTANCES, RawInstance> = UnsafeArcArray::new();
+// loom's UnsafeArcArray can not be created in const context
+#[cfg(loom)]
+static RAW_INSTANCES: std::sync::LazyLock<UnsafeArcArray<MAX_INSTANCES, RawInstance>> =
+    std::sync::LazyLock::new(UnsafeArcArray::new);
+
+/// Returns the number of alive instances. Handy when creating an instance fails due to no free
+/// space
+pub fn live_count() -> usize {
+    RAW_INSTANCES.live_count()
+}
 
-/// A handle to a RawInstance
+/// State of an alive instance in the instance registry
 #[derive(Debug)]
+pub struct InstanceRegistryEntry {
+    pub id: usize,
+    /// Number of Instance handles, not counting the one temporarily held by registry_dump
+    pub ref_count: u64,
+    pub handle: RawInstanceHandle,
+}
+
+/// Returns the state of every alive instance, used to debug leaked or prematurely dropped
+/// Instance handles
+pub fn registry_dump() -> Vec<InstanceRegistryEntry> {
+    (0..RAW_INSTANCES.capacity())
+        .filter(|&id| RAW_INSTANCES.try_inc_count(id))
+        .map(|id| {
+            // try_inc_count succeeded, so this handle owns a reference and will release it when dropped
+            let instance = Instance { id };
+            InstanceRegistryEntry {
+                id,
+                ref_count: RAW_INSTANCES.ref_count(id).saturating_sub(1),
+                handle: instance.as_raw(),
+            }
+        })
+        .collect()
+}
+
+/// A handle to a RawInstance
 pub struct Instance {
     id: usize,
 }
 
+impl Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Instance {{ id: {}, handle: {} }}",
+            self.id,
+            LoggedHandle::new(self.instance.handle())
+        )
+    }
+}
+
 impl Drop for Instance {
     fn drop(&mut self) {
         // Safety: Instance's existence guarantees that the RawInstance is valid
@@ -74,14 +222,133 @@ pub enum InstanceCreateError {
 
 impl Instance {
     /// # Safety
-    /// The ash::Instance should not be destroyed  
+    /// The ash::Instance should not be destroyed. api_version and enabled_extensions must match the
+    /// ones the instance was created with
+    /// # Panics
+    /// Panics if the instance limit is reached
+    pub unsafe fn from_raw(
+        raw_instance: ash::Instance,
+        api_version: ApiVersion,
+        enabled_extensions: Vec<Extension>,
+    ) -> Self {
+        unsafe { Self::try_from_raw(raw_instance, api_version, enabled_extensions) }
+            .unwrap_or_else(|e| e.fatal())
+    }
+
+    /// Same as from_raw, but returns an error if the instance limit is reached. The instance is
+    /// destroyed in that case
+    /// # Safety
+    /// Same as from_raw
+    pub unsafe fn try_from_raw(
+        raw_instance: ash::Instance,
+        api_version: ApiVersion,
+        enabled_extensions: Vec<Extension>,
+    ) -> Result<Self, VkError> {
+        Self::try_init(raw_instance, false, api_version, enabled_extensions)
+    }
+
+    /// Wraps an instance created by another Vulkan library. The instance is not destroyed when the
+    /// last Instance is dropped
+    /// # Safety
+    /// The handle must be a valid instance created with the same Vulkan loader, and must outlive
+    /// every Instance and PhysicalDevice using it. api_version and enabled_extensions must match
+    /// the ones the instance was created with
     /// # Panics
     /// Panics if the instance limit is reached
-    pub unsafe fn from_raw(raw_instance: ash::Instance) -> Self {
-        Self {
-            id: RAW_INSTANCES
-                .acquire_and_init(|| RawInstance(raw_instance))
-                .expect("Failed to initialize instance (no free space)"),
+    pub unsafe fn from_raw_external(
+        handle: RawInstanceHandle,
+        api_version: ApiVersion,
+        enabled_extensions: Vec<Extension>,
+    ) -> Self {
+        unsafe { Self::try_from_raw_external(handle, api_version, enabled_extensions) }
+            .unwrap_or_else(|e| e.fatal())
+    }
+
+    /// Same as from_raw_external, but returns an error if the instance limit is reached
+    /// # Safety
+    /// Same as from_raw_external
+    pub unsafe fn try_from_raw_external(
+        handle: RawInstanceHandle,
+        api_version: ApiVersion,
+        enabled_extensions: Vec<Extension>,
+    ) -> Result<Self, VkError> {
+        // Safety: the caller guarantees that the handle is valid
+        let raw_instance =
+            unsafe { ash::Instance::load(entry::ENTRY.static_fn(), handle.into_raw()) };
+        Self::try_init(raw_instance, true, api_version, enabled_extensions)
+    }
+
+    /// Puts the instance into the registry. If the registry is full, the instance is dropped
+    /// (destroyed unless external) and an error is returned
+    fn try_init(
+        raw_instance: ash::Instance,
+        external: bool,
+        api_version: ApiVersion,
+        enabled_extensions: Vec<Extension>,
+    ) -> Result<Self, VkError> {
+        let surface_fns = enabled_extensions
+            .contains(&Extension::KhrSurface)
+            .then(|| ash::khr::surface::Instance::new(&entry::ENTRY, &raw_instance));
+        #[cfg(feature = "surface-win32")]
+        let win32_surface_fns = enabled_extensions
+            .contains(&Extension::KhrWin32Surface)
+            .then(|| ash::khr::win32_surface::Instance::new(&entry::ENTRY, &raw_instance));
+        #[cfg(feature = "surface-wayland")]
+        let wayland_surface_fns = enabled_extensions
+            .contains(&Extension::KhrWaylandSurface)
+            .then(|| ash::khr::wayland_surface::Instance::new(&entry::ENTRY, &raw_instance));
+        #[cfg(feature = "surface-xcb")]
+        let xcb_surface_fns = enabled_extensions
+            .contains(&Extension::KhrXcbSurface)
+            .then(|| ash::khr::xcb_surface::Instance::new(&entry::ENTRY, &raw_instance));
+        #[cfg(feature = "surface-metal")]
+        let metal_surface_fns = enabled_extensions
+            .contains(&Extension::ExtMetalSurface)
+            .then(|| ash::ext::metal_surface::Instance::new(&entry::ENTRY, &raw_instance));
+        let properties2_fns = enabled_extensions
+            .contains(&Extension::KhrGetPhysicalDeviceProperties2)
+            .then(|| {
+                ash::khr::get_physical_device_properties2::Instance::new(
+                    &entry::ENTRY,
+                    &raw_instance,
+                )
+            });
+
+        let handle = raw_instance.handle();
+        let raw = RawInstance {
+            instance: raw_instance,
+            external,
+            api_version,
+            enabled_extensions,
+            surface_fns,
+            #[cfg(feature = "surface-win32")]
+            win32_surface_fns,
+            #[cfg(feature = "surface-wayland")]
+            wayland_surface_fns,
+            #[cfg(feature = "surface-xcb")]
+            xcb_surface_fns,
+            #[cfg(feature = "surface-metal")]
+            metal_surface_fns,
+            properties2_fns,
+            cooperative_matrix_fns: OnceLock::new(),
+        };
+        // If there is no free slot, the closure is dropped without being called, which drops raw
+        // and destroys the instance
+        match RAW_INSTANCES.acquire_and_init(move || raw) {
+            Some(id) => Ok(Self { id }),
+            None => {
+                log::error!(
+                    target: log_target::INSTANCE,
+                    "[instance.registry_full] No free space for instance {}, {} instances alive",
+                    LoggedHandle::new(handle),
+                    live_count()
+                );
+                Err(VkError::new(
+                    "Failed to initialize instance (no free space)",
+                    VulkanResult::ErrorTooManyObjects,
+                )
+                .with_object(handle, None))
+            }
         }
     }
 
@@ -89,18 +356,46 @@ impl Instance {
     /// # Panics
     /// Panics if vulkan is not supported
     pub fn create_vk_instance(info: InstanceCreateInfo) -> Self {
-        log::trace!("Creating Instance: {info:#?}");
+        Self::try_create_vk_instance(info).unwrap_or_else(|e| e.fatal())
+    }
+
+    /// Creates a vulkan instance. Returns an error if vulkan is not supported
+    pub fn try_create_vk_instance(info: InstanceCreateInfo) -> Result<Self, VkError> {
+        log::trace!(
+            target: log_target::INSTANCE,
+            "[instance.creating] Creating Instance: {info:#?}"
+        );
         let create_info = info.create_raw();
 
         // Safety: InstanceCreateInfo guarantees that it gives valid create_info
-        let instance = expect_vk_success("Failed to create vk::Instance", unsafe {
+        let instance = check_vk_success("Failed to create vk::Instance", unsafe {
             entry::ENTRY.create_instance(&create_info.vk_instance_create_info(), None)
-        });
+        })?;
 
-        log::info!("Cretated instance, handle: {}", instance.handle().as_raw());
+        log::info!(
+            target: log_target::INSTANCE,
+            "[instance.created] Created instance, handle: {}",
+            LoggedHandle::new(instance.handle())
+        );
 
-        // Safety: The only reference to this instance is being put into the array
-        unsafe { Self::from_raw(instance) }
+        let enabled_extensions = info
+            .enabled_extensions
+            .extensions()
+            .iter()
+            .map(|extension| extension.extension())
+            // Unknown extensions are passed to vulkan by name, but can not be queried by variant
+            .filter(|&extension| extension != Extension::UnknownExtension)
+            .collect();
+
+        // The only reference to this instance is being put into the array, the version and
+        // extensions are taken from the info it was created with
+        Self::try_init(
+            instance,
+            false,
+            // api_version of 0 is treated as 1.0 by vulkan
+            ApiVersion::from_raw(info.api_version).max(ApiVersion::V1_0),
+            enabled_extensions,
+        )
     }
 }
 
@@ -111,26 +406,106 @@ impl Clone for Instance {
     }
 }
 
+const _: () = assert_send_sync::<RawInstance>();
+const _: () = assert_send_sync::<Instance>();
+
+/// Name pointers of the enabled layers or extensions. Stored inline, as only a few of them are
+/// usually enabled
+type NamePtrs = SmallVec<[*const c_char; 8]>;
+
+/// Capabilities that VK_LAYER_KHRONOS_profiles replaces with the profile's values
+const SIMULATED_CAPABILITIES: [&CStr; 6] = [
+    c"SIMULATE_API_VERSION_BIT",
+    c"SIMULATE_FEATURES_BIT",
+    c"SIMULATE_PROPERTIES_BIT",
+    c"SIMULATE_EXTENSIONS_BIT",
+    c"SIMULATE_FORMATS_BIT",
+    c"SIMULATE_QUEUE_FAMILY_PROPERTIES_BIT",
+];
+
+/// VK_EXT_layer_settings structs that select the profile of VK_LAYER_KHRONOS_profiles. Boxed by
+/// RawInstanceCreateInfo, as the structs point into each other
+struct ProfileLayerSettings<'a> {
+    profile_file: *const c_char,
+    profile_name: *const c_char,
+    simulate_capabilities: [*const c_char; SIMULATED_CAPABILITIES.len()],
+    settings: [vk::LayerSettingEXT<'a>; 3],
+    info: vk::LayerSettingsCreateInfoEXT<'a>,
+}
+
+impl<'a> ProfileLayerSettings<'a> {
+    fn new(profile: &'a LayerProfile) -> Box<Self> {
+        let mut settings = Box::new(Self {
+            profile_file: profile.file.as_ptr(),
+            profile_name: profile
+                .name
+                .as_ref()
+                .map_or(ptr::null(), |name| name.as_ptr()),
+            simulate_capabilities: SIMULATED_CAPABILITIES.map(CStr::as_ptr),
+            settings: Default::default(),
+            info: Default::default(),
+        });
+
+        let setting =
+            |name: &CStr, values: *const *const c_char, count: usize| vk::LayerSettingEXT {
+                p_layer_name: ValidationLayer::KhronosProfiles.name().as_ptr(),
+                p_setting_name: name.as_ptr(),
+                ty: vk::LayerSettingTypeEXT::STRING,
+                value_count: count as u32,
+                p_values: values.cast(),
+                ..Default::default()
+            };
+        // The box does not move, so the pointers into it stay valid
+        settings.settings[0] = setting(c"profile_file", &settings.profile_file, 1);
+        settings.settings[1] = setting(
+            c"simulate_capabilities",
+            settings.simulate_capabilities.as_ptr(),
+            SIMULATED_CAPABILITIES.len(),
+        );
+        let mut count = 2;
+        if profile.name.is_some() {
+            settings.settings[2] = setting(c"profile_name", &settings.profile_name, 1);
+            count += 1;
+        }
+        settings.info.setting_count = count;
+        settings.info.p_settings = settings.settings.as_ptr();
+        settings
+    }
+}
+
 /// Struct containing pointers to data required to create vk::Instance. This intermediate struct is
 /// needed because of double inderection of the data
 pub struct RawInstanceCreateInfo<'a> {
-    enabled_validation_layers: Vec<*const c_char>, // 'a lifetime referencing InstanceCreateInfo
-    enabled_extension: Vec<*const c_char>,         // 'a lifetime  referencing InstanceCreateInfo
+    enabled_validation_layers: NamePtrs, // 'a lifetime referencing InstanceCreateInfo
+    enabled_extension: NamePtrs,         // 'a lifetime  referencing InstanceCreateInfo
     application_info: vk::ApplicationInfo<'a>,
+    /// Chained to vk::InstanceCreateInfo if a profile is selected
+    profile_settings: Option<Box<ProfileLayerSettings<'a>>>,
     owned_info: &'a InstanceCreateInfo,
 }
 
 impl RawInstanceCreateInfo<'_> {
     /// Creates the actual vk::InstanceCreateInfo from self's data pointers
     pub fn vk_instance_create_info(&self) -> vk::InstanceCreateInfo<'_> {
-        vk::InstanceCreateInfo::default()
+        let mut info = vk::InstanceCreateInfo::default()
             .flags(self.owned_info.flags)
             .enabled_layer_names(&self.enabled_validation_layers)
             .enabled_extension_names(&self.enabled_extension)
-            .application_info(&self.application_info)
+            .application_info(&self.application_info);
+        if let Some(settings) = &self.profile_settings {
+            info.p_next = (&settings.info as *const vk::LayerSettingsCreateInfoEXT).cast();
+        }
+        info
     }
 }
 
+/// Profile loaded by VK_LAYER_KHRONOS_profiles
+#[derive(Debug)]
+struct LayerProfile {
+    file: CString,
+    name: Option<CString>,
+}
+
 /// Owned data for vk::InstanceCreateInfo
 #[derive(Debug)]
 pub struct InstanceCreateInfo {
@@ -146,11 +521,32 @@ pub struct InstanceCreateInfo {
     engine_version: u32,
 
     api_version: u32,
+
+    profile: Option<LayerProfile>,
+}
+
+/// Returned by InstanceCreateInfo::new
+#[derive(Debug, thiserror::Error)]
+pub enum InstanceCreateInfoError {
+    #[error("Name contains a null byte")]
+    Nul(#[from] NulError),
+    #[error("Extension {extension:?} is provided by layer {layer:?}, which is not enabled")]
+    LayerNotEnabled { extension: CString, layer: CString },
+    #[error("A profile is selected, but VK_LAYER_KHRONOS_profiles is not enabled")]
+    ProfilesLayerNotEnabled,
 }
 
 #[bon::bon]
 impl InstanceCreateInfo {
-    /// Creates InstanceCreateInfo. Fails if any of the given strings contain nulls
+    /// Creates InstanceCreateInfo. Fails if any of the given strings contain nulls, or if an
+    /// extension is only provided by a layer that is not in validation_layers.
+    ///
+    /// profile_file selects a Vulkan profiles JSON file for VK_LAYER_KHRONOS_profiles, which must
+    /// be in validation_layers. The layer then reports the profile's API version, features,
+    /// properties, extensions, formats and queue families to every query of the instance, so
+    /// the raw_properties* and raw_features* wrappers return the profile's values. profile_name
+    /// picks one of the profiles in the file and is ignored without profile_file. By default the
+    /// layer uses the first profile
     #[builder]
     pub fn new(
         validation_layers: Option<AvailableValidationLayers>,
@@ -161,7 +557,9 @@ impl InstanceCreateInfo {
         engine_name: Option<&[u8]>,
         engine_version: Option<u32>,
         api_version: u32,
-    ) -> Result<Self, NulError> {
+        profile_file: Option<&Path>,
+        profile_name: Option<&[u8]>,
+    ) -> Result<Self, InstanceCreateInfoError> {
         let application_name = if let Some(name) = application_name {
             CString::new(name)?
         } else {
@@ -177,6 +575,39 @@ impl InstanceCreateInfo {
         let enabled_validation_layers = validation_layers.unwrap_or_default();
         let enabled_extensions = extensions.unwrap_or_default();
 
+        // Layer-provided extensions are only available while their layer is enabled
+        for extension in enabled_extensions.extensions() {
+            let Some(layer) = extension.provided_by_layer() else {
+                continue;
+            };
+            if !enabled_validation_layers
+                .layers()
+                .iter()
+                .any(|enabled| enabled.name() == layer)
+            {
+                return Err(InstanceCreateInfoError::LayerNotEnabled {
+                    extension: extension.name().to_owned(),
+                    layer: layer.to_owned(),
+                });
+            }
+        }
+
+        let profile = match profile_file {
+            Some(file) => Some(LayerProfile {
+                file: CString::new(file.as_os_str().as_encoded_bytes())?,
+                name: profile_name.map(CString::new).transpose()?,
+            }),
+            None => None,
+        };
+        if profile.is_some()
+            && !enabled_validation_layers
+                .layers()
+                .iter()
+                .any(|enabled| enabled.layer() == ValidationLayer::KhronosProfiles)
+        {
+            return Err(InstanceCreateInfoError::ProfilesLayerNotEnabled);
+        }
+
         let mut flags = vk::InstanceCreateFlags::empty();
         if enumerate_portability.is_some_and(|c| c) {
             flags |= vk::InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
@@ -194,6 +625,7 @@ impl InstanceCreateInfo {
             engine_name,
             engine_version,
             api_version,
+            profile,
         })
     }
 
@@ -201,17 +633,18 @@ impl InstanceCreateInfo {
     pub fn create_raw(&self) -> RawInstanceCreateInfo<'_> {
         let extension_name_ptrs = self
             .enabled_extensions
-            .names()
+            .extensions()
             .iter()
-            .map(|&s: &&CStr| s.as_ptr())
-            .collect::<Vec<_>>();
+            .map(|extension| extension.name().as_ptr())
+            .collect();
 
+        // The layer order is significant and must be preserved
         let validation_layer_name_ptrs = self
             .enabled_validation_layers
-            .names()
+            .layers()
             .iter()
-            .map(|&s: &&CStr| s.as_ptr())
-            .collect::<Vec<_>>();
+            .map(|layer| layer.name().as_ptr())
+            .collect();
 
         let application_info = vk::ApplicationInfo::default()
             .application_name(self.application_name.as_c_str())
@@ -224,6 +657,7 @@ impl InstanceCreateInfo {
             enabled_validation_layers: validation_layer_name_ptrs,
             enabled_extension: extension_name_ptrs,
             application_info,
+            profile_settings: self.profile.as_ref().map(ProfileLayerSettings::new),
             owned_info: self,
         }
     }
@@ -245,6 +679,51 @@ mod test {
         let _ = Instance::create_vk_instance(info);
     }
 
+    #[test]
+    fn registry_diagnostics() {
+        let info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_0)
+            .build()
+            .unwrap();
+        let instance = Instance::create_vk_instance(info);
+        let clone = instance.clone();
+
+        assert!(live_count() >= 1);
+
+        let dump = registry_dump();
+        let entry = dump
+            .iter()
+            .find(|entry| entry.id == instance.id)
+            .expect("Instance not found in the registry");
+        // Other tests can not hold clones of this instance
+        assert_eq!(entry.ref_count, 2);
+        // Safety: instance is not destroyed
+        assert_eq!(
+            entry.handle.into_raw(),
+            unsafe { clone.get_raw_ref() }.handle()
+        );
+    }
+
+    #[test]
+    fn external_instance() {
+        let info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+        let instance = Instance::create_vk_instance(info);
+        assert!(!instance.is_external());
+
+        // Safety: instance outlives the imported copy and was created with these parameters
+        let external =
+            unsafe { Instance::from_raw_external(instance.as_raw(), ApiVersion::V1_1, Vec::new()) };
+        assert!(external.is_external());
+        assert_eq!(external.as_raw(), instance.as_raw());
+        drop(external);
+
+        // Dropping the external copy must not destroy the instance
+        assert!(!crate::vk::physical_device::enumerate(&instance).is_empty());
+    }
+
     #[test]
     fn khronos_validation() {
         use crate::vk::validation_layer::{self, *};
@@ -293,7 +772,9 @@ mod test {
             .extensions(extensions)
             .build()
             .unwrap();
-        let _ = Instance::create_vk_instance(info);
+        let instance = Instance::create_vk_instance(info);
+        assert!(instance.surface_fns().is_some());
+        assert!(instance.properties2_fns().is_none());
     }
 
     #[test]
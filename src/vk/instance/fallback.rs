@@ -0,0 +1,239 @@
+//!
+//! Instance creation that falls back to simpler configurations on systems lacking layers,
+//! extensions or drivers
+//!
+
+use crate::vk::{
+    Extension, Instance, ValidationLayer, assert_send_sync,
+    error::VkError,
+    extension::{self, AvailableExtensions},
+    instance::InstanceCreateInfo,
+    log_target,
+    validation_layer::{self, AvailableValidationLayers},
+};
+
+/// A configuration tried by FallbackChain. The chain's required extensions are added to the
+/// configuration's own
+#[derive(Clone, Debug)]
+pub struct FallbackConfig {
+    /// Used to report which configuration succeeded
+    pub name: &'static str,
+    pub validation_layers: Vec<ValidationLayer>,
+    pub extensions: Vec<Extension>,
+    /// Enables VK_KHR_portability_enumeration, so non-conformant implementations like MoltenVK
+    /// are listed
+    pub enumerate_portability: bool,
+}
+
+/// Why a configuration was rejected
+#[derive(thiserror::Error, Debug)]
+pub enum FallbackFailure {
+    #[error("missing validation layers")]
+    MissingLayers,
+    #[error("missing extensions")]
+    MissingExtensions,
+    #[error(transparent)]
+    Vulkan(#[from] VkError),
+}
+
+/// Every configuration of a FallbackChain failed
+#[derive(thiserror::Error, Debug)]
+#[error("all instance configurations failed: {}", describe(.attempts))]
+pub struct FallbackError {
+    /// Name of each tried configuration with the reason it failed, in order
+    pub attempts: Vec<(&'static str, FallbackFailure)>,
+}
+
+fn describe(attempts: &[(&'static str, FallbackFailure)]) -> String {
+    attempts
+        .iter()
+        .map(|(name, failure)| {
+            format!(
+                "{name} ({})",
+                failure.to_string().lines().next().unwrap_or("")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// An instance created by FallbackChain
+#[derive(Debug)]
+pub struct FallbackInstance {
+    pub instance: Instance,
+    /// Name of the configuration that succeeded
+    pub config: &'static str,
+    /// Configurations tried before, with the reasons they failed
+    pub skipped: Vec<(&'static str, FallbackFailure)>,
+}
+
+/// Tries instance configurations in order until one succeeds
+#[derive(Clone, Debug)]
+pub struct FallbackChain {
+    api_version: u32,
+    required_extensions: Vec<Extension>,
+    configs: Vec<FallbackConfig>,
+}
+
+impl FallbackChain {
+    /// Creates an empty chain. required_extensions are enabled in every configuration
+    pub fn new(api_version: u32, required_extensions: &[Extension]) -> Self {
+        Self {
+            api_version,
+            required_extensions: required_extensions.to_vec(),
+            configs: Vec::new(),
+        }
+    }
+
+    /// The usual startup chain: validation with debug utils, then no validation, then
+    /// portability enumeration
+    pub fn standard(api_version: u32, required_extensions: &[Extension]) -> Self {
+        Self::new(api_version, required_extensions)
+            .then(FallbackConfig {
+                name: "validation",
+                validation_layers: vec![ValidationLayer::KhronosValidation],
+                extensions: vec![Extension::ExtDebugUtils],
+                enumerate_portability: false,
+            })
+            .then(FallbackConfig {
+                name: "no validation",
+                validation_layers: Vec::new(),
+                extensions: Vec::new(),
+                enumerate_portability: false,
+            })
+            .then(FallbackConfig {
+                name: "portability",
+                validation_layers: Vec::new(),
+                extensions: vec![Extension::KhrPortabilityEnumeration],
+                enumerate_portability: true,
+            })
+    }
+
+    /// Appends a configuration to try after the existing ones
+    pub fn then(mut self, config: FallbackConfig) -> Self {
+        self.configs.push(config);
+        self
+    }
+
+    /// Configurations in the order they are tried
+    pub fn configs(&self) -> &[FallbackConfig] {
+        &self.configs
+    }
+
+    /// Creates an instance with the first configuration that succeeds
+    pub fn create(&self) -> Result<FallbackInstance, FallbackError> {
+        let mut attempts = Vec::new();
+        for config in &self.configs {
+            match self.try_config(config) {
+                Ok(instance) => {
+                    log::info!(
+                        target: log_target::INSTANCE,
+                        "[instance.fallback.succeeded] Created instance with the {:?} configuration",
+                        config.name
+                    );
+                    return Ok(FallbackInstance {
+                        instance,
+                        config: config.name,
+                        skipped: attempts,
+                    });
+                }
+                Err(failure) => {
+                    log::warn!(
+                        target: log_target::INSTANCE,
+                        "[instance.fallback.failed] Instance configuration {:?} failed: {failure}",
+                        config.name
+                    );
+                    attempts.push((config.name, failure));
+                }
+            }
+        }
+        Err(FallbackError { attempts })
+    }
+
+    fn try_config(&self, config: &FallbackConfig) -> Result<Instance, FallbackFailure> {
+        let available_layers = validation_layer::try_enumerate()?;
+        let layers = AvailableValidationLayers::from_available_and_required(
+            &available_layers,
+            &config.validation_layers,
+        )
+        .ok_or(FallbackFailure::MissingLayers)?;
+
+        let required: Vec<_> = self
+            .required_extensions
+            .iter()
+            .chain(&config.extensions)
+            .copied()
+            .collect();
+        let available_extensions = extension::try_enumerate_with_layers(&layers)?;
+        let extensions =
+            AvailableExtensions::from_available_and_required(&available_extensions, &required)
+                .ok_or(FallbackFailure::MissingExtensions)?;
+
+        let info = InstanceCreateInfo::builder()
+            .validation_layers(layers)
+            .extensions(extensions)
+            .enumerate_portability(config.enumerate_portability)
+            .api_version(self.api_version)
+            .build()
+            .expect("no names are passed, extensions are enumerated with the enabled layers");
+        Ok(Instance::try_create_vk_instance(info)?)
+    }
+}
+
+const _: () = assert_send_sync::<FallbackChain>();
+const _: () = assert_send_sync::<FallbackInstance>();
+
+#[cfg(test)]
+mod test {
+    use ash::vk;
+
+    use super::*;
+
+    #[test]
+    fn standard_order() {
+        let chain = FallbackChain::standard(vk::API_VERSION_1_1, &[]);
+        let names: Vec<_> = chain.configs().iter().map(|config| config.name).collect();
+        assert_eq!(names, ["validation", "no validation", "portability"]);
+    }
+
+    #[test]
+    fn error_lists_attempts() {
+        let error = FallbackError {
+            attempts: vec![
+                ("validation", FallbackFailure::MissingLayers),
+                ("portability", FallbackFailure::MissingExtensions),
+            ],
+        };
+        assert_eq!(
+            error.to_string(),
+            "all instance configurations failed: validation (missing validation layers), \
+             portability (missing extensions)"
+        );
+    }
+
+    #[test]
+    fn falls_back() {
+        let fallback = FallbackChain::new(vk::API_VERSION_1_1, &[])
+            .then(FallbackConfig {
+                name: "unreachable",
+                validation_layers: Vec::new(),
+                extensions: vec![Extension::UnreachableExtension],
+                enumerate_portability: false,
+            })
+            .then(FallbackConfig {
+                name: "plain",
+                validation_layers: Vec::new(),
+                extensions: Vec::new(),
+                enumerate_portability: false,
+            })
+            .create()
+            .unwrap();
+
+        assert_eq!(fallback.config, "plain");
+        assert_eq!(fallback.skipped.len(), 1);
+        assert!(matches!(
+            fallback.skipped[0],
+            ("unreachable", FallbackFailure::MissingExtensions)
+        ));
+    }
+}
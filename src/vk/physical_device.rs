@@ -2,28 +2,117 @@
 //! Utilities for safe vulkan physical device information querying
 //!
 
-use std::fmt::Debug;
+pub mod arithmetic;
+pub mod profile;
+#[cfg(feature = "raytracing")]
+pub mod raytracing;
+pub mod requirements;
+pub mod subgroup;
+pub mod topology;
+
+use std::{collections::HashSet, ffi::CStr, fmt::Debug};
 
 use ash::vk;
 
-use crate::vk::{Instance, error::expect_vk_success};
+use crate::vk::{
+    ApiVersion, Instance, LoggedHandle, assert_send_sync,
+    error::{VkError, check_vk_success},
+    log_target,
+    raw_handle::RawPhysicalDeviceHandle,
+    version::UnsupportedVersion,
+};
+
+/// Returned by the try_* queries that need a minimum Vulkan version and also enumerate the device
+/// extensions
+#[derive(Debug, thiserror::Error)]
+pub enum DeviceQueryError {
+    #[error(transparent)]
+    UnsupportedVersion(#[from] UnsupportedVersion),
+    #[error(transparent)]
+    Vulkan(#[from] VkError),
+}
+
+impl DeviceQueryError {
+    /// Panics on a vulkan error like VkError::fatal and returns the unsupported version otherwise.
+    /// Used by the query functions without the try_ prefix
+    pub(crate) fn unsupported_or_fatal(self) -> UnsupportedVersion {
+        match self {
+            Self::UnsupportedVersion(e) => e,
+            Self::Vulkan(e) => e.fatal(),
+        }
+    }
+}
 
 /// Properties of an available queue family. Guarantees that the queue family is available on the
 /// stored device
-#[derive(Debug)]
 pub struct AvailableQueue {
     device: vk::PhysicalDevice,
     idx: usize,
     flags: vk::QueueFlags,
-    // TODO: add the rest of the properties
+    queue_count: u32,
+    timestamp_valid_bits: u32,
+    min_image_transfer_granularity: vk::Extent3D,
+    /// Only queried by get_available_queues_2 on devices supporting VK_KHR_video_queue
+    #[cfg(feature = "video")]
+    video_codec_operations: Option<vk::VideoCodecOperationFlagsKHR>,
+}
+
+impl Debug for AvailableQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("AvailableQueue");
+        debug
+            .field("device", &LoggedHandle::new(self.device))
+            .field("idx", &self.idx)
+            .field("flags", &self.flags)
+            .field("queue_count", &self.queue_count)
+            .field("timestamp_valid_bits", &self.timestamp_valid_bits)
+            .field(
+                "min_image_transfer_granularity",
+                &self.min_image_transfer_granularity,
+            );
+        #[cfg(feature = "video")]
+        debug.field("video_codec_operations", &self.video_codec_operations);
+        debug.finish()
+    }
 }
 
 impl AvailableQueue {
+    /// Capabilities of the queues in the family
+    pub fn flags(&self) -> vk::QueueFlags {
+        self.flags
+    }
+
     /// Checks if the queue has the graphics bit
     pub fn has_graphics(&self) -> bool {
         self.flags.contains(vk::QueueFlags::GRAPHICS)
     }
 
+    /// Checks if the queue has the compute bit
+    pub fn has_compute(&self) -> bool {
+        self.flags.contains(vk::QueueFlags::COMPUTE)
+    }
+
+    /// Checks if the queue has the transfer bit. Graphics and compute queues support transfers
+    /// even if the bit is not set
+    pub fn has_transfer(&self) -> bool {
+        self.flags.contains(vk::QueueFlags::TRANSFER)
+    }
+
+    /// Checks if the queue supports video decode operations
+    pub fn has_video_decode(&self) -> bool {
+        self.flags.contains(vk::QueueFlags::VIDEO_DECODE_KHR)
+    }
+
+    /// Checks if the queue supports video encode operations
+    pub fn has_video_encode(&self) -> bool {
+        self.flags.contains(vk::QueueFlags::VIDEO_ENCODE_KHR)
+    }
+
+    /// Checks if the queue supports VK_NV_optical_flow operations
+    pub fn has_optical_flow(&self) -> bool {
+        self.flags.contains(vk::QueueFlags::OPTICAL_FLOW_NV)
+    }
+
     /// Checks if the queue belongs to the given physical device
     pub fn belongs_to_device(&self, device: &PhysicalDevice) -> bool {
         device.device == self.device
@@ -34,7 +123,44 @@ impl AvailableQueue {
         self.idx
     }
 
-    fn from_family_prop(prop: vk::QueueFamilyProperties) -> Self {}
+    /// Number of queues in the queue family
+    pub fn queue_count(&self) -> u32 {
+        self.queue_count
+    }
+
+    /// Number of meaningful bits in timestamps written by the queues. 0 if timestamps are not
+    /// supported
+    pub fn timestamp_valid_bits(&self) -> u32 {
+        self.timestamp_valid_bits
+    }
+
+    /// Minimum granularity of image transfers on the queues
+    pub fn min_image_transfer_granularity(&self) -> vk::Extent3D {
+        self.min_image_transfer_granularity
+    }
+
+    /// Video codec operations supported by the queues. None if they were not queried
+    #[cfg(feature = "video")]
+    pub fn video_codec_operations(&self) -> Option<vk::VideoCodecOperationFlagsKHR> {
+        self.video_codec_operations
+    }
+
+    fn from_family_prop(
+        device: vk::PhysicalDevice,
+        idx: usize,
+        prop: vk::QueueFamilyProperties,
+    ) -> Self {
+        Self {
+            device,
+            idx,
+            flags: prop.queue_flags,
+            queue_count: prop.queue_count,
+            timestamp_valid_bits: prop.timestamp_valid_bits,
+            min_image_transfer_granularity: prop.min_image_transfer_granularity,
+            #[cfg(feature = "video")]
+            video_codec_operations: None,
+        }
+    }
 }
 
 /// A handle to a vk::PhysicalDevice. Can only be acquired from enumerating physical devices,
@@ -46,7 +172,12 @@ pub struct PhysicalDevice {
 
 impl Debug for PhysicalDevice {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "PhysicalDevice {:?} of {:?}", self.device, self.instance)
+        write!(
+            f,
+            "PhysicalDevice {} of {:?}",
+            LoggedHandle::new(self.device),
+            self.instance
+        )
     }
 }
 
@@ -56,6 +187,11 @@ impl PhysicalDevice {
         self.device
     }
 
+    /// Raw handle for passing the device to other Vulkan libraries
+    pub fn as_raw(&self) -> RawPhysicalDeviceHandle {
+        RawPhysicalDeviceHandle::from_raw(self.device)
+    }
+
     /// Query PhysicalDeviceProperties
     pub fn raw_properties(&self) -> vk::PhysicalDeviceProperties {
         // Safety: instance is not destroyed, a valid PhysicalDevice is passed
@@ -76,6 +212,100 @@ impl PhysicalDevice {
         }
     }
 
+    /// Query FormatProperties of the given format
+    pub fn raw_format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        // Safety: instance is not destroyed, a valid PhysicalDevice is passed
+        unsafe {
+            self.instance
+                .get_raw_ref()
+                .get_physical_device_format_properties(self.device, format)
+        }
+    }
+
+    /// API version supported by the device
+    pub fn api_version(&self) -> ApiVersion {
+        ApiVersion::from_raw(self.raw_properties().api_version)
+    }
+
+    /// API version that can be used with the device: the lower of the device's and the instance's
+    /// versions
+    pub fn usable_api_version(&self) -> ApiVersion {
+        self.api_version().min(self.instance.api_version())
+    }
+
+    /// Query PhysicalDeviceProperties2, filling the structures chained to props. Falls back to
+    /// VK_KHR_get_physical_device_properties2 if the instance uses Vulkan 1.0
+    pub fn raw_properties2(
+        &self,
+        props: &mut vk::PhysicalDeviceProperties2<'_>,
+    ) -> Result<(), UnsupportedVersion> {
+        // Safety: instance is not destroyed, a valid PhysicalDevice is passed, the function is
+        // only called if the version or the extension allows it
+        unsafe {
+            let instance = self.instance.get_raw_ref();
+            if self.instance.api_version() >= ApiVersion::V1_1 {
+                instance.get_physical_device_properties2(self.device, props);
+            } else if let Some(fns) = self.instance.properties2_fns() {
+                fns.get_physical_device_properties2(self.device, props);
+            } else {
+                return self.instance.api_version().require(ApiVersion::V1_1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Query PhysicalDeviceFeatures2, filling the structures chained to features. Falls back to
+    /// VK_KHR_get_physical_device_properties2 if the instance uses Vulkan 1.0
+    pub fn raw_features2(
+        &self,
+        features: &mut vk::PhysicalDeviceFeatures2<'_>,
+    ) -> Result<(), UnsupportedVersion> {
+        // Safety: instance is not destroyed, a valid PhysicalDevice is passed, the function is
+        // only called if the version or the extension allows it
+        unsafe {
+            let instance = self.instance.get_raw_ref();
+            if self.instance.api_version() >= ApiVersion::V1_1 {
+                instance.get_physical_device_features2(self.device, features);
+            } else if let Some(fns) = self.instance.properties2_fns() {
+                fns.get_physical_device_features2(self.device, features);
+            } else {
+                return self.instance.api_version().require(ApiVersion::V1_1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Query the device extensions supported by the device
+    pub fn raw_extension_properties(&self) -> Vec<vk::ExtensionProperties> {
+        self.try_raw_extension_properties()
+            .unwrap_or_else(|e| e.fatal())
+    }
+
+    /// Query the device extensions supported by the device
+    pub fn try_raw_extension_properties(&self) -> Result<Vec<vk::ExtensionProperties>, VkError> {
+        // Safety: instance is not destroyed, a valid PhysicalDevice is passed
+        check_vk_success("Failed to enumerate device extensions", unsafe {
+            self.instance
+                .get_raw_ref()
+                .enumerate_device_extension_properties(self.device)
+        })
+        .map_err(|e| e.with_object(self.device, None))
+    }
+
+    /// Checks if the device supports the device extension with the given name
+    pub fn supports_extension(&self, name: &CStr) -> bool {
+        self.try_supports_extension(name)
+            .unwrap_or_else(|e| e.fatal())
+    }
+
+    /// Checks if the device supports the device extension with the given name
+    pub fn try_supports_extension(&self, name: &CStr) -> Result<bool, VkError> {
+        Ok(self
+            .try_raw_extension_properties()?
+            .iter()
+            .any(|prop| prop.extension_name_as_c_str() == Ok(name)))
+    }
+
     /// Query QueueFamilyProperties
     pub fn raw_queue_family_properties(&self) -> Vec<vk::QueueFamilyProperties> {
         // Safety: instance is not destroyed, a valid PhysicalDevice is passed
@@ -86,26 +316,145 @@ impl PhysicalDevice {
         }
     }
 
+    /// Number of queue families, used to size the slice passed to raw_queue_family_properties2
+    pub fn raw_queue_family_properties2_len(&self) -> Result<usize, UnsupportedVersion> {
+        // Safety: instance is not destroyed, a valid PhysicalDevice is passed, the function is
+        // only called if the version or the extension allows it
+        unsafe {
+            let instance = self.instance.get_raw_ref();
+            if self.instance.api_version() >= ApiVersion::V1_1 {
+                Ok(instance.get_physical_device_queue_family_properties2_len(self.device))
+            } else if let Some(fns) = self.instance.properties2_fns() {
+                Ok(fns.get_physical_device_queue_family_properties2_len(self.device))
+            } else {
+                // Neither Vulkan 1.1 nor the extension is available
+                Err(UnsupportedVersion {
+                    required: ApiVersion::V1_1,
+                    available: self.instance.api_version(),
+                })
+            }
+        }
+    }
+
+    /// Query QueueFamilyProperties2, filling the structures chained to each element of props.
+    /// props must have raw_queue_family_properties2_len elements. Falls back to
+    /// VK_KHR_get_physical_device_properties2 if the instance uses Vulkan 1.0
+    pub fn raw_queue_family_properties2(
+        &self,
+        props: &mut [vk::QueueFamilyProperties2<'_>],
+    ) -> Result<(), UnsupportedVersion> {
+        // Safety: instance is not destroyed, a valid PhysicalDevice is passed, the function is
+        // only called if the version or the extension allows it
+        unsafe {
+            let instance = self.instance.get_raw_ref();
+            if self.instance.api_version() >= ApiVersion::V1_1 {
+                instance.get_physical_device_queue_family_properties2(self.device, props);
+            } else if let Some(fns) = self.instance.properties2_fns() {
+                fns.get_physical_device_queue_family_properties2(self.device, props);
+            } else {
+                return self.instance.api_version().require(ApiVersion::V1_1);
+            }
+        }
+        Ok(())
+    }
+
     /// Get a vec of avalilable queue families.
     pub fn get_available_queues(&self) -> Vec<AvailableQueue> {
         self.raw_queue_family_properties()
             .into_iter()
             .enumerate()
-            .flat_map(|(idx, prop)| AvailableQueueFamily {
-                device: self.device,
-                idx,
-                flags: prop.queue_flags,
-                queue_count: prop.queue_count,
-            })
+            .map(|(idx, prop)| AvailableQueue::from_family_prop(self.device, idx, prop))
             .collect()
     }
+
+    /// Get a vec of avalilable queue families using the properties2 path. With the video feature,
+    /// also queries the video codec operations if the device supports VK_KHR_video_queue
+    pub fn get_available_queues_2(&self) -> Result<Vec<AvailableQueue>, UnsupportedVersion> {
+        self.try_get_available_queues_2()
+            .map_err(DeviceQueryError::unsupported_or_fatal)
+    }
+
+    /// Get a vec of avalilable queue families using the properties2 path. With the video feature,
+    /// also queries the video codec operations if the device supports VK_KHR_video_queue
+    pub fn try_get_available_queues_2(&self) -> Result<Vec<AvailableQueue>, DeviceQueryError> {
+        let len = self.raw_queue_family_properties2_len()?;
+        #[cfg(feature = "video")]
+        if self.try_supports_extension(ash::khr::video_queue::NAME)? {
+            return self.available_queues_with_video(len);
+        }
+
+        let mut props = vec![vk::QueueFamilyProperties2::default(); len];
+        self.raw_queue_family_properties2(&mut props)?;
+        Ok(props
+            .iter()
+            .enumerate()
+            .map(|(idx, prop)| {
+                AvailableQueue::from_family_prop(self.device, idx, prop.queue_family_properties)
+            })
+            .collect())
+    }
+
+    /// get_available_queues_2 with QueueFamilyVideoPropertiesKHR chained to each family. The
+    /// device must support VK_KHR_video_queue
+    #[cfg(feature = "video")]
+    fn available_queues_with_video(
+        &self,
+        len: usize,
+    ) -> Result<Vec<AvailableQueue>, DeviceQueryError> {
+        let mut video = vec![vk::QueueFamilyVideoPropertiesKHR::default(); len];
+        let mut props: Vec<_> = video
+            .iter_mut()
+            .map(|video| vk::QueueFamilyProperties2::default().push_next(video))
+            .collect();
+        self.raw_queue_family_properties2(&mut props)?;
+
+        let queues = props
+            .iter()
+            .enumerate()
+            .map(|(idx, prop)| {
+                AvailableQueue::from_family_prop(self.device, idx, prop.queue_family_properties)
+            })
+            .collect::<Vec<_>>();
+        // props borrows video mutably, so the codec operations are read after it is dropped
+        drop(props);
+
+        Ok(queues
+            .into_iter()
+            .zip(video)
+            .map(|(queue, video)| AvailableQueue {
+                video_codec_operations: Some(video.video_codec_operations),
+                ..queue
+            })
+            .collect())
+    }
 }
+
+/// Names of the extensions in props, for queries that check several extensions against a single
+/// enumeration
+fn extension_name_set(props: &[vk::ExtensionProperties]) -> HashSet<&CStr> {
+    props
+        .iter()
+        .filter_map(|prop| prop.extension_name_as_c_str().ok())
+        .collect()
+}
+
+const _: () = assert_send_sync::<PhysicalDevice>();
+const _: () = assert_send_sync::<AvailableQueue>();
+
 /// Enumerate avalilable vulkan physical devices
 pub fn enumerate(instance: &Instance) -> Vec<PhysicalDevice> {
+    try_enumerate(instance).unwrap_or_else(|e| e.fatal())
+}
+
+/// Enumerate avalilable vulkan physical devices
+pub fn try_enumerate(instance: &Instance) -> Result<Vec<PhysicalDevice>, VkError> {
     // Safety: instacne is not destroyed
-    let devices = expect_vk_success("Failed to enumerate_physical_devices", unsafe {
-        instance.get_raw_ref().enumerate_physical_devices()
-    });
+    let raw_instance = unsafe { instance.get_raw_ref() };
+    // Safety: raw_instance is valid
+    let devices = check_vk_success("Failed to enumerate_physical_devices", unsafe {
+        raw_instance.enumerate_physical_devices()
+    })
+    .map_err(|e| e.with_object(raw_instance.handle(), None))?;
 
     let devices = devices
         .into_iter()
@@ -114,8 +463,11 @@ pub fn enumerate(instance: &Instance) -> Vec<PhysicalDevice> {
             instance: instance.clone(),
         })
         .collect();
-    log::trace!("Enumerated physical devices, avalilable devices: {devices:#?}");
-    devices
+    log::trace!(
+        target: log_target::DEVICE,
+        "[device.enumerated] Enumerated physical devices, avalilable devices: {devices:#?}"
+    );
+    Ok(devices)
 }
 
 #[cfg(test)]
@@ -141,6 +493,54 @@ mod test {
         let _ = devices[0].raw_device();
         let _ = devices[0].raw_properties();
         let _ = devices[0].raw_features();
+        assert!(devices[0].usable_api_version() <= devices[0].api_version());
+    }
+
+    #[test]
+    fn properties2_version_gating() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_0)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        let mut props = vk::PhysicalDeviceProperties2::default();
+        assert_eq!(
+            devices[0].raw_properties2(&mut props),
+            Err(UnsupportedVersion {
+                required: ApiVersion::V1_1,
+                available: ApiVersion::V1_0
+            })
+        );
+    }
+
+    #[test]
+    fn properties2() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        let mut props = vk::PhysicalDeviceProperties2::default();
+        devices[0].raw_properties2(&mut props).unwrap();
+        assert_eq!(
+            props.properties.device_id,
+            devices[0].raw_properties().device_id
+        );
+
+        let mut features = vk::PhysicalDeviceFeatures2::default();
+        devices[0].raw_features2(&mut features).unwrap();
     }
 
     #[test]
@@ -173,16 +573,46 @@ mod test {
         assert!(!devices.is_empty());
 
         let graphic_families = devices[0]
-            .get_available_queue_families()
+            .get_available_queues()
             .into_iter()
             .enumerate()
             .filter(|(_idx, qf)| qf.has_graphics())
             .collect::<Vec<_>>();
 
-        assert!(graphic_families.len() > 0);
+        assert!(!graphic_families.is_empty());
         assert!(graphic_families[0].1.has_graphics());
         assert!(graphic_families[0].1.queue_count() > 0);
         assert!(graphic_families[0].1.belongs_to_device(&devices[0]));
-        assert_eq!(graphic_families[0].1.get_idx(), graphic_families[0].0);
+        assert_eq!(
+            graphic_families[0].1.get_family_idx(),
+            graphic_families[0].0
+        );
+    }
+
+    #[test]
+    fn available_queues_2() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        let queues = devices[0].get_available_queues();
+        let queues_2 = devices[0].get_available_queues_2().unwrap();
+        assert_eq!(queues.len(), queues_2.len());
+        for (queue, queue_2) in queues.iter().zip(&queues_2) {
+            assert_eq!(queue.flags(), queue_2.flags());
+            assert_eq!(queue.queue_count(), queue_2.queue_count());
+            assert_eq!(queue.timestamp_valid_bits(), queue_2.timestamp_valid_bits());
+            assert_eq!(
+                queue.min_image_transfer_granularity(),
+                queue_2.min_image_transfer_granularity()
+            );
+        }
     }
 }
@@ -0,0 +1,194 @@
+//!
+//! Reduced precision arithmetic capabilities, relevant for ML workloads
+//!
+
+use ash::vk;
+
+use crate::vk::{
+    ApiVersion,
+    error::check_vk_success,
+    physical_device::{DeviceQueryError, PhysicalDevice, extension_name_set},
+    version::UnsupportedVersion,
+};
+
+/// Support of reduced precision shader arithmetic and storage
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ArithmeticFeatures {
+    pub shader_float16: bool,
+    pub shader_int8: bool,
+    pub shader_int16: bool,
+    pub storage_buffer_16bit_access: bool,
+    pub storage_buffer_8bit_access: bool,
+    pub shader_integer_dot_product: bool,
+    pub cooperative_matrix: bool,
+}
+
+/// A matrix multiply-add shape supported by VK_KHR_cooperative_matrix
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CooperativeMatrixShape {
+    pub m_size: u32,
+    pub n_size: u32,
+    pub k_size: u32,
+    pub a_type: vk::ComponentTypeKHR,
+    pub b_type: vk::ComponentTypeKHR,
+    pub c_type: vk::ComponentTypeKHR,
+    pub result_type: vk::ComponentTypeKHR,
+    pub saturating_accumulation: bool,
+    pub scope: vk::ScopeKHR,
+}
+
+impl PhysicalDevice {
+    /// Query reduced precision arithmetic features. Features whose extension is not supported and
+    /// not promoted to the usable API version are reported as unsupported. Requires Vulkan 1.1
+    pub fn arithmetic_features(&self) -> Result<ArithmeticFeatures, UnsupportedVersion> {
+        self.try_arithmetic_features()
+            .map_err(DeviceQueryError::unsupported_or_fatal)
+    }
+
+    /// Query reduced precision arithmetic features. Features whose extension is not supported and
+    /// not promoted to the usable API version are reported as unsupported. Requires Vulkan 1.1
+    pub fn try_arithmetic_features(&self) -> Result<ArithmeticFeatures, DeviceQueryError> {
+        let version = self.usable_api_version();
+        version.require(ApiVersion::V1_1)?;
+
+        let extension_properties = self.try_raw_extension_properties()?;
+        let extensions = extension_name_set(&extension_properties);
+        let has_float16_int8 =
+            version >= ApiVersion::V1_2 || extensions.contains(ash::khr::shader_float16_int8::NAME);
+        let has_8bit_storage =
+            version >= ApiVersion::V1_2 || extensions.contains(ash::khr::_8bit_storage::NAME);
+        let has_dot_product = version >= ApiVersion::V1_3
+            || extensions.contains(ash::khr::shader_integer_dot_product::NAME);
+        let has_cooperative_matrix = extensions.contains(ash::khr::cooperative_matrix::NAME);
+
+        let mut storage16 = vk::PhysicalDevice16BitStorageFeatures::default();
+        let mut float16_int8 = vk::PhysicalDeviceShaderFloat16Int8Features::default();
+        let mut storage8 = vk::PhysicalDevice8BitStorageFeatures::default();
+        let mut dot_product = vk::PhysicalDeviceShaderIntegerDotProductFeatures::default();
+        let mut cooperative_matrix = vk::PhysicalDeviceCooperativeMatrixFeaturesKHR::default();
+        let shader_int16 = {
+            // Structs of unsupported extensions must not be chained
+            let mut features = vk::PhysicalDeviceFeatures2::default().push_next(&mut storage16);
+            if has_float16_int8 {
+                features = features.push_next(&mut float16_int8);
+            }
+            if has_8bit_storage {
+                features = features.push_next(&mut storage8);
+            }
+            if has_dot_product {
+                features = features.push_next(&mut dot_product);
+            }
+            if has_cooperative_matrix {
+                features = features.push_next(&mut cooperative_matrix);
+            }
+            self.raw_features2(&mut features)?;
+            features.features.shader_int16
+        };
+
+        Ok(ArithmeticFeatures {
+            shader_float16: float16_int8.shader_float16 == vk::TRUE,
+            shader_int8: float16_int8.shader_int8 == vk::TRUE,
+            shader_int16: shader_int16 == vk::TRUE,
+            storage_buffer_16bit_access: storage16.storage_buffer16_bit_access == vk::TRUE,
+            storage_buffer_8bit_access: storage8.storage_buffer8_bit_access == vk::TRUE,
+            shader_integer_dot_product: dot_product.shader_integer_dot_product == vk::TRUE,
+            cooperative_matrix: cooperative_matrix.cooperative_matrix == vk::TRUE,
+        })
+    }
+
+    /// Query the matrix shapes supported by VK_KHR_cooperative_matrix. Returns an empty Vec if
+    /// the extension is not supported. Requires Vulkan 1.1 or VK_KHR_get_physical_device_properties2
+    pub fn cooperative_matrix_shapes(
+        &self,
+    ) -> Result<Vec<CooperativeMatrixShape>, UnsupportedVersion> {
+        self.try_cooperative_matrix_shapes()
+            .map_err(DeviceQueryError::unsupported_or_fatal)
+    }
+
+    /// Query the matrix shapes supported by VK_KHR_cooperative_matrix. Returns an empty Vec if
+    /// the extension is not supported. Requires Vulkan 1.1 or VK_KHR_get_physical_device_properties2
+    pub fn try_cooperative_matrix_shapes(
+        &self,
+    ) -> Result<Vec<CooperativeMatrixShape>, DeviceQueryError> {
+        // VK_KHR_cooperative_matrix depends on VK_KHR_get_physical_device_properties2
+        if self.instance.api_version() < ApiVersion::V1_1
+            && self.instance.properties2_fns().is_none()
+        {
+            self.instance.api_version().require(ApiVersion::V1_1)?;
+        }
+        if !self.try_supports_extension(ash::khr::cooperative_matrix::NAME)? {
+            return Ok(Vec::new());
+        }
+
+        // Safety: a valid PhysicalDevice that supports the extension is passed
+        let props = check_vk_success("Failed to get cooperative matrix properties", unsafe {
+            self.instance
+                .cooperative_matrix_fns()
+                .get_physical_device_cooperative_matrix_properties(self.device)
+        })
+        .map_err(|e| e.with_object(self.device, None))?;
+
+        Ok(props
+            .iter()
+            .map(|prop| CooperativeMatrixShape {
+                m_size: prop.m_size,
+                n_size: prop.n_size,
+                k_size: prop.k_size,
+                a_type: prop.a_type,
+                b_type: prop.b_type,
+                c_type: prop.c_type,
+                result_type: prop.result_type,
+                saturating_accumulation: prop.saturating_accumulation == vk::TRUE,
+                scope: prop.scope,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vk::{Instance, instance::InstanceCreateInfo, physical_device};
+
+    #[test]
+    fn query() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = physical_device::enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        // Output cannot be verified, but it must be ensured that the queries do not panic or fault
+        let _ = devices[0].arithmetic_features().unwrap();
+        for shape in devices[0].cooperative_matrix_shapes().unwrap() {
+            assert_ne!(shape.m_size * shape.n_size * shape.k_size, 0);
+        }
+    }
+
+    #[test]
+    fn cooperative_matrix_version_gating() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_0)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = physical_device::enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        assert_eq!(
+            devices[0].cooperative_matrix_shapes(),
+            Err(UnsupportedVersion {
+                required: ApiVersion::V1_1,
+                available: ApiVersion::V1_0
+            })
+        );
+    }
+}
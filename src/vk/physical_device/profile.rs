@@ -0,0 +1,366 @@
+//!
+//! Checks against the Khronos roadmap profiles using baked requirement tables
+//!
+
+use std::ffi::CStr;
+
+use ash::vk;
+
+use crate::vk::{
+    ApiVersion,
+    error::VkError,
+    physical_device::{
+        PhysicalDevice,
+        requirements::{
+            CompatibilityReport, DeviceRequirements, FeatureRequirement, LimitRequirement,
+        },
+    },
+};
+
+/// A Khronos roadmap profile. The profile's API version, extensions, Vulkan 1.0 to 1.3 features
+/// and Vulkan 1.0 limits are checked. The requirements on the property structs of later versions
+/// and on the feature structs of extensions are not
+#[derive(Clone, Copy, PartialEq, Eq, Debug, strum::EnumIter, strum::Display)]
+pub enum Profile {
+    /// VP_KHR_roadmap_2022
+    Roadmap2022,
+    /// VP_KHR_roadmap_2024, includes Roadmap2022
+    Roadmap2024,
+}
+
+const ROADMAP_2022_EXTENSIONS: &[&CStr] = &[c"VK_KHR_global_priority"];
+
+const ROADMAP_2024_EXTENSIONS: &[&CStr] = &[
+    c"VK_KHR_dynamic_rendering_local_read",
+    c"VK_KHR_load_store_op_none",
+    c"VK_KHR_shader_quad_control",
+    c"VK_KHR_shader_maximal_reconvergence",
+    c"VK_KHR_shader_subgroup_uniform_control_flow",
+    c"VK_KHR_shader_subgroup_rotate",
+    c"VK_KHR_shader_float_controls2",
+    c"VK_KHR_shader_expect_assume",
+    c"VK_KHR_line_rasterization",
+    c"VK_KHR_vertex_attribute_divisor",
+    c"VK_KHR_index_type_uint8",
+    c"VK_KHR_map_memory2",
+    c"VK_KHR_maintenance5",
+    c"VK_KHR_push_descriptor",
+];
+
+/// Builds a LimitRequirement named after the checked expression
+macro_rules! limit {
+    ($limits:ident => $check:expr) => {
+        LimitRequirement {
+            name: stringify!($check),
+            check: |$limits| $check,
+        }
+    };
+}
+
+/// Builds a FeatureRequirement named after the checked feature
+macro_rules! feature {
+    ($features:ident => $feature:expr) => {
+        FeatureRequirement {
+            name: stringify!($feature),
+            check: |$features| $feature == vk::TRUE,
+        }
+    };
+}
+
+const ROADMAP_2022_VERSION_FEATURES: &[FeatureRequirement] = &[
+    feature!(f => f.vulkan11.sampler_ycbcr_conversion),
+    feature!(f => f.vulkan12.sampler_mirror_clamp_to_edge),
+    feature!(f => f.vulkan12.descriptor_indexing),
+    feature!(f => f.vulkan12.shader_uniform_texel_buffer_array_dynamic_indexing),
+    feature!(f => f.vulkan12.shader_storage_texel_buffer_array_dynamic_indexing),
+    feature!(f => f.vulkan12.shader_sampled_image_array_non_uniform_indexing),
+    feature!(f => f.vulkan12.shader_storage_buffer_array_non_uniform_indexing),
+    feature!(f => f.vulkan12.shader_uniform_texel_buffer_array_non_uniform_indexing),
+    feature!(f => f.vulkan12.descriptor_binding_sampled_image_update_after_bind),
+    feature!(f => f.vulkan12.descriptor_binding_storage_image_update_after_bind),
+    feature!(f => f.vulkan12.descriptor_binding_storage_buffer_update_after_bind),
+    feature!(f => f.vulkan12.descriptor_binding_uniform_texel_buffer_update_after_bind),
+    feature!(f => f.vulkan12.descriptor_binding_storage_texel_buffer_update_after_bind),
+    feature!(f => f.vulkan12.descriptor_binding_update_unused_while_pending),
+    feature!(f => f.vulkan12.descriptor_binding_partially_bound),
+    feature!(f => f.vulkan12.descriptor_binding_variable_descriptor_count),
+    feature!(f => f.vulkan12.runtime_descriptor_array),
+    feature!(f => f.vulkan12.scalar_block_layout),
+    feature!(f => f.vulkan13.dynamic_rendering),
+    feature!(f => f.vulkan13.synchronization2),
+    feature!(f => f.vulkan13.maintenance4),
+];
+
+const ROADMAP_2024_VERSION_FEATURES: &[FeatureRequirement] = &[
+    feature!(f => f.vulkan11.shader_draw_parameters),
+    feature!(f => f.vulkan11.storage_buffer16_bit_access),
+    feature!(f => f.vulkan12.shader_int8),
+    feature!(f => f.vulkan12.shader_float16),
+    feature!(f => f.vulkan12.storage_buffer8_bit_access),
+];
+
+const ROADMAP_2022_LIMITS: &[LimitRequirement] = &[
+    limit!(l => l.max_image_dimension1_d >= 8192),
+    limit!(l => l.max_image_dimension2_d >= 8192),
+    limit!(l => l.max_image_dimension_cube >= 8192),
+    limit!(l => l.max_image_array_layers >= 2048),
+    limit!(l => l.max_uniform_buffer_range >= 65536),
+    limit!(l => l.buffer_image_granularity <= 4096),
+    limit!(l => l.max_per_stage_descriptor_samplers >= 64),
+    limit!(l => l.max_per_stage_descriptor_uniform_buffers >= 15),
+    limit!(l => l.max_per_stage_descriptor_storage_buffers >= 30),
+    limit!(l => l.max_per_stage_descriptor_sampled_images >= 200),
+    limit!(l => l.max_per_stage_descriptor_storage_images >= 16),
+    limit!(l => l.max_per_stage_resources >= 200),
+    limit!(l => l.max_descriptor_set_samplers >= 576),
+    limit!(l => l.max_descriptor_set_uniform_buffers >= 90),
+    limit!(l => l.max_descriptor_set_storage_buffers >= 96),
+    limit!(l => l.max_descriptor_set_sampled_images >= 1800),
+    limit!(l => l.max_descriptor_set_storage_images >= 144),
+    limit!(l => l.max_fragment_combined_output_resources >= 16),
+    limit!(l => l.max_compute_work_group_invocations >= 256),
+    limit!(l => l.max_compute_work_group_size[0] >= 256),
+    limit!(l => l.max_compute_work_group_size[1] >= 256),
+    limit!(l => l.max_compute_work_group_size[2] >= 64),
+    limit!(l => l.sub_texel_precision_bits >= 8),
+    limit!(l => l.mipmap_precision_bits >= 6),
+    limit!(l => l.max_sampler_lod_bias >= 14.0),
+    limit!(l => l.point_size_granularity <= 0.125),
+    limit!(l => l.line_width_granularity <= 0.5),
+    limit!(l => l.standard_sample_locations == vk::TRUE),
+    limit!(l => l.max_color_attachments >= 7),
+];
+
+const ROADMAP_2024_LIMITS: &[LimitRequirement] = &[
+    limit!(l => l.max_bound_descriptor_sets >= 7),
+    limit!(l => l.max_color_attachments >= 8),
+    limit!(l => l.timestamp_compute_and_graphics == vk::TRUE),
+];
+
+fn roadmap_2022_features() -> vk::PhysicalDeviceFeatures {
+    vk::PhysicalDeviceFeatures::default()
+        .full_draw_index_uint32(true)
+        .image_cube_array(true)
+        .independent_blend(true)
+        .sample_rate_shading(true)
+        .draw_indirect_first_instance(true)
+        .depth_bias_clamp(true)
+        .sampler_anisotropy(true)
+        .occlusion_query_precise(true)
+        .fragment_stores_and_atomics(true)
+        .shader_storage_image_extended_formats(true)
+        .shader_uniform_buffer_array_dynamic_indexing(true)
+        .shader_sampled_image_array_dynamic_indexing(true)
+        .shader_storage_buffer_array_dynamic_indexing(true)
+        .shader_storage_image_array_dynamic_indexing(true)
+}
+
+impl Profile {
+    /// Requirements of the profile that are checked by PhysicalDevice::supports_profile
+    pub fn requirements(self) -> DeviceRequirements {
+        let roadmap_2022 = DeviceRequirements::builder()
+            .api_version(ApiVersion::V1_3)
+            .extensions(ROADMAP_2022_EXTENSIONS.to_vec())
+            .features(roadmap_2022_features())
+            .version_features(ROADMAP_2022_VERSION_FEATURES.to_vec())
+            .limits(ROADMAP_2022_LIMITS.to_vec())
+            .build();
+
+        match self {
+            Self::Roadmap2022 => roadmap_2022,
+            Self::Roadmap2024 => DeviceRequirements {
+                extensions: [ROADMAP_2022_EXTENSIONS, ROADMAP_2024_EXTENSIONS].concat(),
+                features: roadmap_2022_features()
+                    .multi_draw_indirect(true)
+                    .shader_image_gather_extended(true)
+                    .shader_int16(true),
+                version_features: [ROADMAP_2022_VERSION_FEATURES, ROADMAP_2024_VERSION_FEATURES]
+                    .concat(),
+                limits: [ROADMAP_2022_LIMITS, ROADMAP_2024_LIMITS].concat(),
+                ..roadmap_2022
+            },
+        }
+    }
+}
+
+impl PhysicalDevice {
+    /// Checks the device against the profile's requirement table, see Profile for what is
+    /// covered. Requires the instance to use Vulkan 1.3
+    pub fn supports_profile(&self, profile: Profile) -> bool {
+        self.profile_report(profile).is_compatible()
+    }
+
+    /// Same as supports_profile, but lists every unmet requirement
+    pub fn profile_report(&self, profile: Profile) -> CompatibilityReport {
+        profile.requirements().check(self)
+    }
+
+    /// Same as supports_profile, but returns an error if the device can not be queried
+    pub fn try_supports_profile(&self, profile: Profile) -> Result<bool, VkError> {
+        Ok(self.try_profile_report(profile)?.is_compatible())
+    }
+
+    /// Same as profile_report, but returns an error if the device can not be queried
+    pub fn try_profile_report(&self, profile: Profile) -> Result<CompatibilityReport, VkError> {
+        profile.requirements().try_check(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vk::{
+        Instance,
+        instance::InstanceCreateInfo,
+        physical_device::{
+            self,
+            requirements::{VersionFeatures, missing_version_features},
+        },
+    };
+
+    /// Version features of a device that meets Roadmap 2022
+    fn roadmap_2022_device() -> VersionFeatures {
+        VersionFeatures {
+            vulkan11: vk::PhysicalDeviceVulkan11Features::default().sampler_ycbcr_conversion(true),
+            vulkan12: vk::PhysicalDeviceVulkan12Features::default()
+                .sampler_mirror_clamp_to_edge(true)
+                .descriptor_indexing(true)
+                .shader_uniform_texel_buffer_array_dynamic_indexing(true)
+                .shader_storage_texel_buffer_array_dynamic_indexing(true)
+                .shader_sampled_image_array_non_uniform_indexing(true)
+                .shader_storage_buffer_array_non_uniform_indexing(true)
+                .shader_uniform_texel_buffer_array_non_uniform_indexing(true)
+                .descriptor_binding_sampled_image_update_after_bind(true)
+                .descriptor_binding_storage_image_update_after_bind(true)
+                .descriptor_binding_storage_buffer_update_after_bind(true)
+                .descriptor_binding_uniform_texel_buffer_update_after_bind(true)
+                .descriptor_binding_storage_texel_buffer_update_after_bind(true)
+                .descriptor_binding_update_unused_while_pending(true)
+                .descriptor_binding_partially_bound(true)
+                .descriptor_binding_variable_descriptor_count(true)
+                .runtime_descriptor_array(true)
+                .scalar_block_layout(true),
+            vulkan13: vk::PhysicalDeviceVulkan13Features::default()
+                .dynamic_rendering(true)
+                .synchronization2(true)
+                .maintenance4(true),
+        }
+    }
+
+    /// Version features of a device that meets Roadmap 2024
+    fn roadmap_2024_device() -> VersionFeatures {
+        let features = roadmap_2022_device();
+        VersionFeatures {
+            vulkan11: features
+                .vulkan11
+                .shader_draw_parameters(true)
+                .storage_buffer16_bit_access(true),
+            vulkan12: features
+                .vulkan12
+                .shader_int8(true)
+                .shader_float16(true)
+                .storage_buffer8_bit_access(true),
+            ..features
+        }
+    }
+
+    #[test]
+    fn version_features() {
+        let mut no_ycbcr = roadmap_2022_device();
+        no_ycbcr.vulkan11.sampler_ycbcr_conversion = vk::FALSE;
+        let mut no_sync2 = roadmap_2024_device();
+        no_sync2.vulkan13.synchronization2 = vk::FALSE;
+        let all_2022: Vec<_> = ROADMAP_2022_VERSION_FEATURES
+            .iter()
+            .map(|feature| feature.name)
+            .collect();
+        let only_2024: Vec<_> = ROADMAP_2024_VERSION_FEATURES
+            .iter()
+            .map(|feature| feature.name)
+            .collect();
+
+        let cases: [(Profile, VersionFeatures, &[&str]); 6] = [
+            (Profile::Roadmap2022, roadmap_2022_device(), &[]),
+            (Profile::Roadmap2022, roadmap_2024_device(), &[]),
+            (
+                Profile::Roadmap2022,
+                no_ycbcr,
+                &["f.vulkan11.sampler_ycbcr_conversion"],
+            ),
+            // A device below Vulkan 1.2 reports the defaults
+            (Profile::Roadmap2022, VersionFeatures::default(), &all_2022),
+            (Profile::Roadmap2024, roadmap_2022_device(), &only_2024),
+            (
+                Profile::Roadmap2024,
+                no_sync2,
+                &["f.vulkan13.synchronization2"],
+            ),
+        ];
+        for (profile, features, missing) in cases {
+            let requirements = profile.requirements();
+            assert_eq!(
+                missing_version_features(&requirements.version_features, &features),
+                missing,
+                "{profile}"
+            );
+        }
+    }
+
+    #[test]
+    fn roadmap_2024_includes_2022() {
+        let roadmap_2022 = Profile::Roadmap2022.requirements();
+        let roadmap_2024 = Profile::Roadmap2024.requirements();
+
+        for extension in &roadmap_2022.extensions {
+            assert!(roadmap_2024.extensions.contains(extension));
+        }
+        for feature in &roadmap_2022.version_features {
+            assert!(
+                roadmap_2024
+                    .version_features
+                    .iter()
+                    .any(|feature_2024| feature_2024.name == feature.name)
+            );
+        }
+        for limit in &roadmap_2022.limits {
+            assert!(
+                roadmap_2024
+                    .limits
+                    .iter()
+                    .any(|limit_2024| limit_2024.name == limit.name)
+            );
+        }
+        assert_eq!(roadmap_2024.features.sampler_anisotropy, vk::TRUE);
+    }
+
+    #[test]
+    fn limit_names() {
+        assert_eq!(
+            ROADMAP_2022_LIMITS[0].name,
+            "l.max_image_dimension1_d >= 8192"
+        );
+        let limits = vk::PhysicalDeviceLimits::default().max_image_dimension1_d(8192);
+        assert!((ROADMAP_2022_LIMITS[0].check)(&limits));
+        assert!(!(ROADMAP_2022_LIMITS[1].check)(&limits));
+    }
+
+    #[test]
+    fn query() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_3)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = physical_device::enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        // Output cannot be verified, but a supported profile must have no unmet requirements
+        for profile in [Profile::Roadmap2022, Profile::Roadmap2024] {
+            let report = devices[0].profile_report(profile);
+            assert_eq!(devices[0].supports_profile(profile), report.is_compatible());
+        }
+    }
+}
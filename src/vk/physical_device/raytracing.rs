@@ -0,0 +1,167 @@
+//!
+//! Ray tracing support of physical devices
+//!
+
+use ash::vk;
+
+use crate::vk::{
+    ApiVersion,
+    physical_device::{DeviceQueryError, PhysicalDevice, extension_name_set},
+    version::UnsupportedVersion,
+};
+
+/// Ray tracing capabilities of a device. Features of unsupported extensions are reported as
+/// unsupported
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RayTracingSupport {
+    /// VK_KHR_acceleration_structure
+    pub acceleration_structure: bool,
+    /// VK_KHR_ray_tracing_pipeline
+    pub ray_tracing_pipeline: bool,
+    /// VK_KHR_ray_query
+    pub ray_query: bool,
+    /// 0 if ray tracing pipelines are not supported
+    pub max_ray_recursion_depth: u32,
+    /// 0 if ray tracing pipelines are not supported
+    pub shader_group_handle_size: u32,
+    /// 0 if acceleration structures are not supported
+    pub max_geometry_count: u64,
+}
+
+impl RayTracingSupport {
+    fn from_structs(
+        acceleration_structure: Option<(
+            &vk::PhysicalDeviceAccelerationStructureFeaturesKHR,
+            &vk::PhysicalDeviceAccelerationStructurePropertiesKHR,
+        )>,
+        pipeline: Option<(
+            &vk::PhysicalDeviceRayTracingPipelineFeaturesKHR,
+            &vk::PhysicalDeviceRayTracingPipelinePropertiesKHR,
+        )>,
+        ray_query: Option<&vk::PhysicalDeviceRayQueryFeaturesKHR>,
+    ) -> Self {
+        let mut support = Self::default();
+        if let Some((features, properties)) = acceleration_structure
+            && features.acceleration_structure == vk::TRUE
+        {
+            support.acceleration_structure = true;
+            support.max_geometry_count = properties.max_geometry_count;
+        }
+        if let Some((features, properties)) = pipeline
+            && features.ray_tracing_pipeline == vk::TRUE
+        {
+            support.ray_tracing_pipeline = true;
+            support.max_ray_recursion_depth = properties.max_ray_recursion_depth;
+            support.shader_group_handle_size = properties.shader_group_handle_size;
+        }
+        support.ray_query = ray_query.is_some_and(|features| features.ray_query == vk::TRUE);
+        support
+    }
+}
+
+impl PhysicalDevice {
+    /// Query ray tracing support. Requires Vulkan 1.1
+    pub fn ray_tracing_support(&self) -> Result<RayTracingSupport, UnsupportedVersion> {
+        self.try_ray_tracing_support()
+            .map_err(DeviceQueryError::unsupported_or_fatal)
+    }
+
+    /// Query ray tracing support. Requires Vulkan 1.1
+    pub fn try_ray_tracing_support(&self) -> Result<RayTracingSupport, DeviceQueryError> {
+        self.usable_api_version().require(ApiVersion::V1_1)?;
+
+        let extension_properties = self.try_raw_extension_properties()?;
+        let extensions = extension_name_set(&extension_properties);
+        let has_acceleration_structure =
+            extensions.contains(ash::khr::acceleration_structure::NAME);
+        let has_pipeline = extensions.contains(ash::khr::ray_tracing_pipeline::NAME);
+        let has_ray_query = extensions.contains(ash::khr::ray_query::NAME);
+
+        let mut acceleration_features =
+            vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default();
+        let mut acceleration_properties =
+            vk::PhysicalDeviceAccelerationStructurePropertiesKHR::default();
+        let mut pipeline_features = vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default();
+        let mut pipeline_properties = vk::PhysicalDeviceRayTracingPipelinePropertiesKHR::default();
+        let mut ray_query_features = vk::PhysicalDeviceRayQueryFeaturesKHR::default();
+        {
+            // Structs of unsupported extensions must not be chained
+            let mut features = vk::PhysicalDeviceFeatures2::default();
+            let mut properties = vk::PhysicalDeviceProperties2::default();
+            if has_acceleration_structure {
+                features = features.push_next(&mut acceleration_features);
+                properties = properties.push_next(&mut acceleration_properties);
+            }
+            if has_pipeline {
+                features = features.push_next(&mut pipeline_features);
+                properties = properties.push_next(&mut pipeline_properties);
+            }
+            if has_ray_query {
+                features = features.push_next(&mut ray_query_features);
+            }
+            self.raw_features2(&mut features)?;
+            self.raw_properties2(&mut properties)?;
+        }
+
+        Ok(RayTracingSupport::from_structs(
+            has_acceleration_structure
+                .then_some((&acceleration_features, &acceleration_properties)),
+            has_pipeline.then_some((&pipeline_features, &pipeline_properties)),
+            has_ray_query.then_some(&ray_query_features),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vk::{Instance, instance::InstanceCreateInfo, physical_device};
+
+    #[test]
+    fn from_structs() {
+        let acceleration_features = vk::PhysicalDeviceAccelerationStructureFeaturesKHR::default()
+            .acceleration_structure(true);
+        let acceleration_properties =
+            vk::PhysicalDeviceAccelerationStructurePropertiesKHR::default()
+                .max_geometry_count(1 << 24);
+        let pipeline_features = vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::default();
+        let pipeline_properties = vk::PhysicalDeviceRayTracingPipelinePropertiesKHR::default()
+            .max_ray_recursion_depth(31);
+
+        let support = RayTracingSupport::from_structs(
+            Some((&acceleration_features, &acceleration_properties)),
+            Some((&pipeline_features, &pipeline_properties)),
+            None,
+        );
+        assert!(support.acceleration_structure);
+        assert_eq!(support.max_geometry_count, 1 << 24);
+        // The extension is supported, but the feature is not
+        assert!(!support.ray_tracing_pipeline);
+        assert_eq!(support.max_ray_recursion_depth, 0);
+        assert!(!support.ray_query);
+
+        assert_eq!(
+            RayTracingSupport::from_structs(None, None, None),
+            RayTracingSupport::default()
+        );
+    }
+
+    #[test]
+    fn query() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = physical_device::enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        // Output cannot be verified, but pipelines can not be supported without acceleration
+        // structures
+        let support = devices[0].ray_tracing_support().unwrap();
+        assert!(support.acceleration_structure || !support.ray_tracing_pipeline);
+    }
+}
@@ -0,0 +1,327 @@
+//!
+//! Checking a physical device against the requirements of an application without creating a
+//! logical device
+//!
+
+use std::{
+    ffi::CStr,
+    fmt::{self, Display},
+    mem::size_of,
+    ptr,
+};
+
+use ash::vk;
+
+use crate::vk::{
+    ApiVersion, assert_send_sync,
+    error::VkError,
+    physical_device::{PhysicalDevice, extension_name_set},
+    version::UnsupportedVersion,
+};
+
+/// A named check of a device limit
+#[derive(Clone, Copy, Debug)]
+pub struct LimitRequirement {
+    /// Used to report the failed check
+    pub name: &'static str,
+    pub check: fn(&vk::PhysicalDeviceLimits) -> bool,
+}
+
+/// Feature structs of Vulkan 1.1 to 1.3. Structs of versions above the usable API version are
+/// left at their defaults, so every feature in them reads as unsupported
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VersionFeatures {
+    pub vulkan11: vk::PhysicalDeviceVulkan11Features<'static>,
+    pub vulkan12: vk::PhysicalDeviceVulkan12Features<'static>,
+    pub vulkan13: vk::PhysicalDeviceVulkan13Features<'static>,
+}
+
+/// A named check of the Vulkan 1.1 to 1.3 features
+#[derive(Clone, Copy, Debug)]
+pub struct FeatureRequirement {
+    /// Used to report the failed check
+    pub name: &'static str,
+    pub check: fn(&VersionFeatures) -> bool,
+}
+
+/// Capabilities an application needs from a physical device
+#[derive(bon::Builder, Clone, Debug)]
+pub struct DeviceRequirements {
+    #[builder(default = ApiVersion::V1_0)]
+    pub api_version: ApiVersion,
+    /// Names of the required device extensions
+    #[builder(default)]
+    pub extensions: Vec<&'static CStr>,
+    /// Features that must be supported, every vk::TRUE field is required
+    #[builder(default)]
+    pub features: vk::PhysicalDeviceFeatures,
+    /// Checks of the Vulkan 1.1 to 1.3 features
+    #[builder(default)]
+    pub version_features: Vec<FeatureRequirement>,
+    /// Each entry must be supported by at least one queue family
+    #[builder(default)]
+    pub queues: Vec<vk::QueueFlags>,
+    /// Formats that must support the given features with optimal tiling
+    #[builder(default)]
+    pub formats: Vec<(vk::Format, vk::FormatFeatureFlags)>,
+    #[builder(default)]
+    pub limits: Vec<LimitRequirement>,
+}
+
+/// Result of DeviceRequirements::check. Lists every unmet requirement, not just the first one
+#[derive(Clone, Debug, Default)]
+pub struct CompatibilityReport {
+    pub api_version: Option<UnsupportedVersion>,
+    pub missing_extensions: Vec<&'static CStr>,
+    /// Required features that are not supported, set to vk::TRUE. None if all are supported
+    pub missing_features: Option<vk::PhysicalDeviceFeatures>,
+    /// Names of the failed Vulkan 1.1 to 1.3 feature checks
+    pub missing_version_features: Vec<&'static str>,
+    pub missing_queues: Vec<vk::QueueFlags>,
+    pub unsupported_formats: Vec<(vk::Format, vk::FormatFeatureFlags)>,
+    /// Names of the failed limit checks
+    pub failed_limits: Vec<&'static str>,
+}
+
+impl CompatibilityReport {
+    /// Checks if all requirements are met
+    pub fn is_compatible(&self) -> bool {
+        self.api_version.is_none()
+            && self.missing_extensions.is_empty()
+            && self.missing_features.is_none()
+            && self.missing_version_features.is_empty()
+            && self.missing_queues.is_empty()
+            && self.unsupported_formats.is_empty()
+            && self.failed_limits.is_empty()
+    }
+}
+
+/// One line per unmet requirement
+impl Display for CompatibilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_compatible() {
+            return write!(f, "compatible");
+        }
+        let mut lines = Vec::new();
+        if let Some(version) = &self.api_version {
+            lines.push(version.to_string());
+        }
+        for extension in &self.missing_extensions {
+            lines.push(format!("missing extension {}", extension.to_string_lossy()));
+        }
+        if let Some(features) = &self.missing_features {
+            lines.push(format!("missing features {features:?}"));
+        }
+        for feature in &self.missing_version_features {
+            lines.push(format!("missing feature {feature}"));
+        }
+        for flags in &self.missing_queues {
+            lines.push(format!("no queue family with {flags:?}"));
+        }
+        for (format, features) in &self.unsupported_formats {
+            lines.push(format!("{format:?} does not support {features:?}"));
+        }
+        for limit in &self.failed_limits {
+            lines.push(format!("limit {limit} not met"));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+const FEATURE_COUNT: usize = size_of::<vk::PhysicalDeviceFeatures>() / size_of::<vk::Bool32>();
+
+fn feature_bits(features: &vk::PhysicalDeviceFeatures) -> &[vk::Bool32; FEATURE_COUNT] {
+    // Safety: PhysicalDeviceFeatures is repr(C) and consists only of Bool32 fields
+    unsafe { &*(features as *const vk::PhysicalDeviceFeatures).cast() }
+}
+
+fn feature_bits_mut(features: &mut vk::PhysicalDeviceFeatures) -> &mut [vk::Bool32; FEATURE_COUNT] {
+    // Safety: PhysicalDeviceFeatures is repr(C) and consists only of Bool32 fields
+    unsafe { &mut *(features as *mut vk::PhysicalDeviceFeatures).cast() }
+}
+
+/// Returns the required features that are not supported, or None if all are
+fn missing_features(
+    required: &vk::PhysicalDeviceFeatures,
+    supported: &vk::PhysicalDeviceFeatures,
+) -> Option<vk::PhysicalDeviceFeatures> {
+    let mut missing = vk::PhysicalDeviceFeatures::default();
+    let mut any = false;
+    for ((missing, &required), &supported) in feature_bits_mut(&mut missing)
+        .iter_mut()
+        .zip(feature_bits(required))
+        .zip(feature_bits(supported))
+    {
+        if required == vk::TRUE && supported != vk::TRUE {
+            *missing = vk::TRUE;
+            any = true;
+        }
+    }
+    any.then_some(missing)
+}
+
+/// Returns the names of the failed feature checks
+pub(super) fn missing_version_features(
+    required: &[FeatureRequirement],
+    supported: &VersionFeatures,
+) -> Vec<&'static str> {
+    required
+        .iter()
+        .filter(|feature| !(feature.check)(supported))
+        .map(|feature| feature.name)
+        .collect()
+}
+
+impl PhysicalDevice {
+    /// Query the Vulkan 1.1 to 1.3 feature structs supported by the usable API version. Returns
+    /// the defaults if the usable version is below 1.2, which introduced the structs
+    pub fn version_features(&self) -> VersionFeatures {
+        let version = self.usable_api_version();
+        let mut supported = VersionFeatures::default();
+        if version < ApiVersion::V1_2 {
+            return supported;
+        }
+        {
+            // Structs of unsupported versions must not be chained
+            let mut features = vk::PhysicalDeviceFeatures2::default()
+                .push_next(&mut supported.vulkan11)
+                .push_next(&mut supported.vulkan12);
+            if version >= ApiVersion::V1_3 {
+                features = features.push_next(&mut supported.vulkan13);
+            }
+            self.raw_features2(&mut features)
+                .expect("Vulkan 1.2 includes PhysicalDeviceFeatures2");
+        }
+        // The chain pointed into the dropped PhysicalDeviceFeatures2
+        supported.vulkan11.p_next = ptr::null_mut();
+        supported.vulkan12.p_next = ptr::null_mut();
+        supported.vulkan13.p_next = ptr::null_mut();
+        supported
+    }
+}
+
+impl DeviceRequirements {
+    /// Checks the device against every requirement without creating a logical device
+    pub fn check(&self, device: &PhysicalDevice) -> CompatibilityReport {
+        self.try_check(device).unwrap_or_else(|e| e.fatal())
+    }
+
+    /// Checks the device against every requirement without creating a logical device
+    pub fn try_check(&self, device: &PhysicalDevice) -> Result<CompatibilityReport, VkError> {
+        let properties = device.raw_properties();
+        let extension_properties = device.try_raw_extension_properties()?;
+        let extensions = extension_name_set(&extension_properties);
+        let queues = device.get_available_queues();
+
+        Ok(CompatibilityReport {
+            api_version: device.usable_api_version().require(self.api_version).err(),
+            missing_extensions: self
+                .extensions
+                .iter()
+                .filter(|&&name| !extensions.contains(name))
+                .copied()
+                .collect(),
+            missing_features: missing_features(&self.features, &device.raw_features()),
+            missing_version_features: if self.version_features.is_empty() {
+                Vec::new()
+            } else {
+                missing_version_features(&self.version_features, &device.version_features())
+            },
+            missing_queues: self
+                .queues
+                .iter()
+                .filter(|&&flags| !queues.iter().any(|queue| queue.flags().contains(flags)))
+                .copied()
+                .collect(),
+            unsupported_formats: self
+                .formats
+                .iter()
+                .filter(|&&(format, features)| {
+                    !device
+                        .raw_format_properties(format)
+                        .optimal_tiling_features
+                        .contains(features)
+                })
+                .copied()
+                .collect(),
+            failed_limits: self
+                .limits
+                .iter()
+                .filter(|limit| !(limit.check)(&properties.limits))
+                .map(|limit| limit.name)
+                .collect(),
+        })
+    }
+}
+
+const _: () = assert_send_sync::<DeviceRequirements>();
+const _: () = assert_send_sync::<CompatibilityReport>();
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vk::{Instance, instance::InstanceCreateInfo, physical_device};
+
+    #[test]
+    fn features_diff() {
+        let required = vk::PhysicalDeviceFeatures::default()
+            .geometry_shader(true)
+            .sampler_anisotropy(true);
+        let supported = vk::PhysicalDeviceFeatures::default()
+            .sampler_anisotropy(true)
+            .shader_int64(true);
+
+        let missing = missing_features(&required, &supported).unwrap();
+        assert_eq!(missing.geometry_shader, vk::TRUE);
+        assert_eq!(missing.sampler_anisotropy, vk::FALSE);
+        assert_eq!(missing.shader_int64, vk::FALSE);
+
+        assert!(missing_features(&supported, &supported).is_none());
+    }
+
+    #[test]
+    fn report_display() {
+        assert_eq!(CompatibilityReport::default().to_string(), "compatible");
+
+        let report = CompatibilityReport {
+            missing_extensions: vec![c"VK_KHR_swapchain"],
+            failed_limits: vec!["max_image_dimension2_d >= 16384"],
+            ..Default::default()
+        };
+        assert!(!report.is_compatible());
+        assert_eq!(
+            report.to_string(),
+            "missing extension VK_KHR_swapchain\nlimit max_image_dimension2_d >= 16384 not met"
+        );
+    }
+
+    #[test]
+    fn check() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = physical_device::enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        // Every device must support these
+        let requirements = DeviceRequirements::builder()
+            .queues(vec![vk::QueueFlags::empty()])
+            .limits(vec![LimitRequirement {
+                name: "max_image_dimension2_d >= 4096",
+                check: |limits| limits.max_image_dimension2_d >= 4096,
+            }])
+            .build();
+        assert!(requirements.check(&devices[0]).is_compatible());
+
+        let requirements = DeviceRequirements::builder()
+            .extensions(vec![c"VK_PIBAF_unreachable"])
+            .build();
+        let report = requirements.check(&devices[0]);
+        assert_eq!(report.missing_extensions, [c"VK_PIBAF_unreachable"]);
+    }
+}
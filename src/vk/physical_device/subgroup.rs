@@ -0,0 +1,124 @@
+//!
+//! Subgroup capabilities of a physical device
+//!
+
+use ash::vk;
+
+use crate::vk::{
+    ApiVersion,
+    physical_device::{DeviceQueryError, PhysicalDevice},
+    version::UnsupportedVersion,
+};
+
+/// Subgroup size control limits, from VK_EXT_subgroup_size_control or Vulkan 1.3
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SubgroupSizeControl {
+    pub min_subgroup_size: u32,
+    pub max_subgroup_size: u32,
+    pub max_compute_workgroup_subgroups: u32,
+    /// Shader stages that a required subgroup size can be specified for
+    pub required_subgroup_size_stages: vk::ShaderStageFlags,
+    /// Whether compute pipelines can require full subgroups
+    pub compute_full_subgroups: bool,
+}
+
+/// Subgroup properties of a physical device
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SubgroupProperties {
+    /// Default number of invocations in a subgroup
+    pub subgroup_size: u32,
+    /// Shader stages that support subgroup operations
+    pub supported_stages: vk::ShaderStageFlags,
+    pub supported_operations: vk::SubgroupFeatureFlags,
+    pub quad_operations_in_all_stages: bool,
+    /// None if subgroup size control is not supported
+    pub size_control: Option<SubgroupSizeControl>,
+}
+
+impl SubgroupProperties {
+    /// Checks if all of the given operations are supported
+    pub fn supports(&self, operations: vk::SubgroupFeatureFlags) -> bool {
+        self.supported_operations.contains(operations)
+    }
+}
+
+impl PhysicalDevice {
+    /// Query subgroup properties, including subgroup size control if the device supports it.
+    /// Requires Vulkan 1.1
+    pub fn subgroup_properties(&self) -> Result<SubgroupProperties, UnsupportedVersion> {
+        self.try_subgroup_properties()
+            .map_err(DeviceQueryError::unsupported_or_fatal)
+    }
+
+    /// Query subgroup properties, including subgroup size control if the device supports it.
+    /// Requires Vulkan 1.1
+    pub fn try_subgroup_properties(&self) -> Result<SubgroupProperties, DeviceQueryError> {
+        self.usable_api_version().require(ApiVersion::V1_1)?;
+
+        let has_size_control = self.usable_api_version() >= ApiVersion::V1_3
+            || self.try_supports_extension(ash::ext::subgroup_size_control::NAME)?;
+
+        let mut subgroup = vk::PhysicalDeviceSubgroupProperties::default();
+        let mut size_control = vk::PhysicalDeviceSubgroupSizeControlProperties::default();
+        let mut size_control_features = vk::PhysicalDeviceSubgroupSizeControlFeatures::default();
+        {
+            let mut props = vk::PhysicalDeviceProperties2::default().push_next(&mut subgroup);
+            if has_size_control {
+                props = props.push_next(&mut size_control);
+            }
+            self.raw_properties2(&mut props)?;
+        }
+        if has_size_control {
+            let mut features =
+                vk::PhysicalDeviceFeatures2::default().push_next(&mut size_control_features);
+            self.raw_features2(&mut features)?;
+        }
+
+        let size_control = (size_control_features.subgroup_size_control == vk::TRUE).then_some(
+            SubgroupSizeControl {
+                min_subgroup_size: size_control.min_subgroup_size,
+                max_subgroup_size: size_control.max_subgroup_size,
+                max_compute_workgroup_subgroups: size_control.max_compute_workgroup_subgroups,
+                required_subgroup_size_stages: size_control.required_subgroup_size_stages,
+                compute_full_subgroups: size_control_features.compute_full_subgroups == vk::TRUE,
+            },
+        );
+
+        Ok(SubgroupProperties {
+            subgroup_size: subgroup.subgroup_size,
+            supported_stages: subgroup.supported_stages,
+            supported_operations: subgroup.supported_operations,
+            quad_operations_in_all_stages: subgroup.quad_operations_in_all_stages == vk::TRUE,
+            size_control,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vk::{Instance, instance::InstanceCreateInfo, physical_device};
+
+    #[test]
+    fn query() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = physical_device::enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        let props = devices[0].subgroup_properties().unwrap();
+        assert_ne!(props.subgroup_size, 0);
+        // Basic operations are required to be supported in compute shaders
+        assert!(props.supports(vk::SubgroupFeatureFlags::BASIC));
+        if let Some(size_control) = props.size_control {
+            assert!(size_control.min_subgroup_size <= props.subgroup_size);
+            assert!(props.subgroup_size <= size_control.max_subgroup_size);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//!
+//! Shader core topology normalized over vendor-specific device properties
+//!
+
+use ash::vk;
+
+use crate::vk::{
+    ApiVersion,
+    physical_device::{DeviceQueryError, PhysicalDevice, extension_name_set},
+    version::UnsupportedVersion,
+};
+
+/// Shader core topology of a GPU, used to size compute dispatches for occupancy
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GpuTopology {
+    /// Number of invocations executed together: wavefront size on AMD, warp size on NVIDIA
+    pub subgroup_size: u32,
+    /// Number of compute units (AMD) or streaming multiprocessors (NVIDIA). None if the device
+    /// does not expose it
+    pub core_count: Option<u32>,
+    /// Maximum number of subgroups resident on a single core. None if the device does not
+    /// expose it
+    pub max_subgroups_per_core: Option<u32>,
+}
+
+impl GpuTopology {
+    fn from_properties(
+        subgroup: &vk::PhysicalDeviceSubgroupProperties,
+        amd: Option<&vk::PhysicalDeviceShaderCorePropertiesAMD>,
+        nv: Option<&vk::PhysicalDeviceShaderSMBuiltinsPropertiesNV>,
+    ) -> Self {
+        let mut topology = Self {
+            subgroup_size: subgroup.subgroup_size,
+            core_count: None,
+            max_subgroups_per_core: None,
+        };
+        if let Some(amd) = amd {
+            topology.core_count = Some(
+                amd.shader_engine_count
+                    * amd.shader_arrays_per_engine_count
+                    * amd.compute_units_per_shader_array,
+            );
+            topology.max_subgroups_per_core =
+                Some(amd.simd_per_compute_unit * amd.wavefronts_per_simd);
+        } else if let Some(nv) = nv {
+            topology.core_count = Some(nv.shader_sm_count);
+            topology.max_subgroups_per_core = Some(nv.shader_warps_per_sm);
+        }
+        topology
+    }
+}
+
+impl PhysicalDevice {
+    /// Query the shader core topology. Core counts are read from VK_AMD_shader_core_properties or
+    /// VK_NV_shader_sm_builtins if the device supports either. Requires Vulkan 1.1
+    pub fn gpu_topology(&self) -> Result<GpuTopology, UnsupportedVersion> {
+        self.try_gpu_topology()
+            .map_err(DeviceQueryError::unsupported_or_fatal)
+    }
+
+    /// Query the shader core topology. Core counts are read from VK_AMD_shader_core_properties or
+    /// VK_NV_shader_sm_builtins if the device supports either. Requires Vulkan 1.1
+    pub fn try_gpu_topology(&self) -> Result<GpuTopology, DeviceQueryError> {
+        self.usable_api_version().require(ApiVersion::V1_1)?;
+
+        let extension_properties = self.try_raw_extension_properties()?;
+        let extensions = extension_name_set(&extension_properties);
+        let has_amd = extensions.contains(ash::amd::shader_core_properties::NAME);
+        let has_nv = extensions.contains(ash::nv::shader_sm_builtins::NAME);
+
+        let mut subgroup = vk::PhysicalDeviceSubgroupProperties::default();
+        let mut amd = vk::PhysicalDeviceShaderCorePropertiesAMD::default();
+        let mut nv = vk::PhysicalDeviceShaderSMBuiltinsPropertiesNV::default();
+        {
+            // Structs of unsupported extensions must not be chained
+            let mut props = vk::PhysicalDeviceProperties2::default().push_next(&mut subgroup);
+            if has_amd {
+                props = props.push_next(&mut amd);
+            }
+            if has_nv {
+                props = props.push_next(&mut nv);
+            }
+            self.raw_properties2(&mut props)?;
+        }
+
+        Ok(GpuTopology::from_properties(
+            &subgroup,
+            has_amd.then_some(&amd),
+            has_nv.then_some(&nv),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vk::{Instance, instance::InstanceCreateInfo, physical_device};
+
+    #[test]
+    fn from_vendor_properties() {
+        let subgroup = vk::PhysicalDeviceSubgroupProperties::default().subgroup_size(64);
+        let amd = vk::PhysicalDeviceShaderCorePropertiesAMD::default()
+            .shader_engine_count(4)
+            .shader_arrays_per_engine_count(2)
+            .compute_units_per_shader_array(5)
+            .simd_per_compute_unit(4)
+            .wavefronts_per_simd(10);
+
+        let topology = GpuTopology::from_properties(&subgroup, Some(&amd), None);
+        assert_eq!(topology.subgroup_size, 64);
+        assert_eq!(topology.core_count, Some(40));
+        assert_eq!(topology.max_subgroups_per_core, Some(40));
+
+        let nv = vk::PhysicalDeviceShaderSMBuiltinsPropertiesNV::default()
+            .shader_sm_count(46)
+            .shader_warps_per_sm(48);
+        let topology = GpuTopology::from_properties(&subgroup, None, Some(&nv));
+        assert_eq!(topology.core_count, Some(46));
+        assert_eq!(topology.max_subgroups_per_core, Some(48));
+
+        let topology = GpuTopology::from_properties(&subgroup, None, None);
+        assert_eq!(topology.core_count, None);
+    }
+
+    #[test]
+    fn query() {
+        let instance_info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .build()
+            .unwrap();
+
+        let instance = Instance::create_vk_instance(instance_info);
+
+        let devices = physical_device::enumerate(&instance);
+
+        assert!(!devices.is_empty());
+
+        let topology = devices[0].gpu_topology().unwrap();
+        assert_ne!(topology.subgroup_size, 0);
+    }
+}
@@ -0,0 +1,240 @@
+//!
+//! Timeline semaphore bookkeeping for submissions spread across several queues
+//!
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use strum::EnumCount;
+
+use crate::vk::assert_send_sync;
+
+/// Kind of queue a submission is executed on. Each kind has its own timeline
+#[derive(Clone, Copy, strum::EnumCount, strum::EnumIter, PartialEq, Eq, Debug)]
+#[repr(usize)]
+pub enum QueueKind {
+    Graphics,
+    Compute,
+    Transfer,
+}
+
+/// Source of QueueGraph epochs, so ids of different graphs and of cleared batches never match
+static NEXT_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+fn next_epoch() -> u64 {
+    NEXT_EPOCH.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Handle to a submission added to a QueueGraph. Only valid until the graph is cleared
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SubmissionId {
+    index: usize,
+    epoch: u64,
+}
+
+/// A value on the timeline semaphore of the given queue
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimelinePoint {
+    pub queue: QueueKind,
+    pub value: u64,
+}
+
+#[derive(Debug)]
+struct Submission {
+    signal: TimelinePoint,
+    waits: Vec<TimelinePoint>,
+}
+
+/// Tracks producer/consumer relationships between submissions on different queues and assigns
+/// timeline semaphore values to wait on and signal. Dependencies between submissions on the same
+/// queue do not produce waits, as those must be handled by pipeline barriers
+#[derive(Debug)]
+pub struct QueueGraph {
+    timelines: [u64; QueueKind::COUNT],
+    submissions: Vec<Submission>,
+    /// Changed by clear, so ids handed out before it are rejected
+    epoch: u64,
+}
+
+impl Default for QueueGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueueGraph {
+    pub fn new() -> Self {
+        Self {
+            timelines: [0; QueueKind::COUNT],
+            submissions: Vec::new(),
+            epoch: next_epoch(),
+        }
+    }
+
+    /// Checks if the id was handed out by this graph since it was last cleared
+    pub fn contains(&self, submission: SubmissionId) -> bool {
+        submission.epoch == self.epoch && submission.index < self.submissions.len()
+    }
+
+    fn submission(&self, submission: SubmissionId) -> &Submission {
+        assert!(
+            self.contains(submission),
+            "SubmissionId does not belong to this QueueGraph or was cleared"
+        );
+        &self.submissions[submission.index]
+    }
+
+    /// Adds a submission on the given queue that consumes the results of dependencies. Since a
+    /// dependency can only be referenced after it was added, the graph is always acyclic
+    /// # Panics
+    /// Panics if any of the dependencies does not belong to this graph or was cleared
+    pub fn add_submission(
+        &mut self,
+        queue: QueueKind,
+        dependencies: &[SubmissionId],
+    ) -> SubmissionId {
+        let mut waits: Vec<TimelinePoint> = Vec::new();
+        for dep in dependencies {
+            let point = self.submission(*dep).signal;
+            if point.queue == queue {
+                continue;
+            }
+            match waits.iter_mut().find(|wait| wait.queue == point.queue) {
+                Some(wait) => wait.value = wait.value.max(point.value),
+                None => waits.push(point),
+            }
+        }
+
+        let timeline = &mut self.timelines[queue as usize];
+        *timeline += 1;
+        let signal = TimelinePoint {
+            queue,
+            value: *timeline,
+        };
+
+        self.submissions.push(Submission { signal, waits });
+        SubmissionId {
+            index: self.submissions.len() - 1,
+            epoch: self.epoch,
+        }
+    }
+
+    /// Value that the submission should signal on its queue's timeline semaphore
+    /// # Panics
+    /// Panics if the submission does not belong to this graph or was cleared
+    pub fn signal(&self, submission: SubmissionId) -> TimelinePoint {
+        self.submission(submission).signal
+    }
+
+    /// Values on other queues' timeline semaphores that the submission should wait for
+    /// # Panics
+    /// Panics if the submission does not belong to this graph or was cleared
+    pub fn waits(&self, submission: SubmissionId) -> &[TimelinePoint] {
+        &self.submission(submission).waits
+    }
+
+    /// The last value assigned on the queue's timeline, 0 if nothing was submitted to it
+    pub fn last_value(&self, queue: QueueKind) -> u64 {
+        self.timelines[queue as usize]
+    }
+
+    /// Forgets all submissions while keeping the timelines' values, so the graph can be reused for
+    /// the next batch of submissions. Ids of the forgotten submissions are rejected afterwards
+    pub fn clear(&mut self) {
+        self.submissions.clear();
+        self.epoch = next_epoch();
+    }
+}
+
+const _: () = assert_send_sync::<QueueGraph>();
+
+#[cfg(test)]
+mod test {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    use super::*;
+
+    #[test]
+    fn signal_values_increase_per_queue() {
+        let mut graph = QueueGraph::new();
+        let g1 = graph.add_submission(QueueKind::Graphics, &[]);
+        let c1 = graph.add_submission(QueueKind::Compute, &[]);
+        let g2 = graph.add_submission(QueueKind::Graphics, &[]);
+
+        assert_eq!(graph.signal(g1).value, 1);
+        assert_eq!(graph.signal(c1).value, 1);
+        assert_eq!(graph.signal(g2).value, 2);
+        assert_eq!(graph.last_value(QueueKind::Graphics), 2);
+        assert_eq!(graph.last_value(QueueKind::Transfer), 0);
+    }
+
+    #[test]
+    fn cross_queue_wait() {
+        let mut graph = QueueGraph::new();
+        let upload = graph.add_submission(QueueKind::Transfer, &[]);
+        let compute = graph.add_submission(QueueKind::Compute, &[upload]);
+        let draw = graph.add_submission(QueueKind::Graphics, &[upload, compute]);
+
+        assert_eq!(
+            graph.waits(compute),
+            &[TimelinePoint {
+                queue: QueueKind::Transfer,
+                value: 1
+            }]
+        );
+        assert_eq!(graph.waits(draw).len(), 2);
+    }
+
+    #[test]
+    fn same_queue_and_duplicate_waits() {
+        let mut graph = QueueGraph::new();
+        let c1 = graph.add_submission(QueueKind::Compute, &[]);
+        let c2 = graph.add_submission(QueueKind::Compute, &[c1]);
+        let g1 = graph.add_submission(QueueKind::Graphics, &[c1, c2]);
+
+        assert!(graph.waits(c2).is_empty());
+        assert_eq!(
+            graph.waits(g1),
+            &[TimelinePoint {
+                queue: QueueKind::Compute,
+                value: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn clear_keeps_timelines() {
+        let mut graph = QueueGraph::new();
+        graph.add_submission(QueueKind::Graphics, &[]);
+        graph.clear();
+        let g = graph.add_submission(QueueKind::Graphics, &[]);
+
+        assert_eq!(graph.signal(g).value, 2);
+    }
+
+    #[test]
+    fn stale_id() {
+        let mut graph = QueueGraph::new();
+        let old = graph.add_submission(QueueKind::Graphics, &[]);
+        graph.clear();
+        let new = graph.add_submission(QueueKind::Graphics, &[]);
+
+        assert!(!graph.contains(old));
+        assert!(graph.contains(new));
+        assert!(catch_unwind(AssertUnwindSafe(|| graph.signal(old))).is_err());
+        assert!(
+            catch_unwind(AssertUnwindSafe(|| {
+                graph.add_submission(QueueKind::Compute, &[old])
+            }))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn foreign_id() {
+        let mut graph = QueueGraph::new();
+        let other = QueueGraph::new().add_submission(QueueKind::Graphics, &[]);
+        graph.add_submission(QueueKind::Graphics, &[]);
+
+        assert!(!graph.contains(other));
+    }
+}
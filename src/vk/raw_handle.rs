@@ -0,0 +1,70 @@
+//!
+//! Typed raw handles for passing objects to other Vulkan libraries. The handles are
+//! repr(transparent), so they can cross FFI boundaries as the corresponding Vulkan handle
+//!
+
+use std::fmt;
+
+use ash::vk::{self, Handle};
+
+use super::LoggedHandle;
+
+macro_rules! raw_handle {
+    ($(#[$doc:meta])* $name:ident, $vk:ty) => {
+        $(#[$doc])*
+        #[repr(transparent)]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name($vk);
+
+        impl $name {
+            /// Wraps a handle obtained from another Vulkan library
+            pub fn from_raw(handle: $vk) -> Self {
+                Self(handle)
+            }
+
+            /// Unwraps the Vulkan handle
+            pub fn into_raw(self) -> $vk {
+                self.0
+            }
+
+            /// The handle as an integer, as used by e.g. debug utils object names
+            pub fn as_u64(self) -> u64 {
+                self.0.as_raw()
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), LoggedHandle::new(self.0))
+            }
+        }
+    };
+}
+
+raw_handle!(
+    /// Raw VkInstance exported by Instance::as_raw
+    RawInstanceHandle,
+    vk::Instance
+);
+raw_handle!(
+    /// Raw VkPhysicalDevice exported by PhysicalDevice::as_raw
+    RawPhysicalDeviceHandle,
+    vk::PhysicalDevice
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let raw = vk::Instance::from_raw(0x42);
+        let handle = RawInstanceHandle::from_raw(raw);
+        assert_eq!(handle.as_u64(), 0x42);
+        assert_eq!(handle.into_raw(), raw);
+        assert_eq!(
+            format!("{handle:?}"),
+            format!("RawInstanceHandle({})", LoggedHandle(0x42))
+        );
+    }
+}
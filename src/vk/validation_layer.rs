@@ -1,20 +1,36 @@
-use std::ffi::{CStr, CString};
+use std::{
+    borrow::Cow,
+    ffi::{CStr, CString},
+};
 
-use strum::{EnumCount, IntoEnumIterator};
+use ash::vk;
+use strum::EnumCount;
 
-use super::{entry, error::expect_vk_success};
+use super::{
+    assert_send_sync, entry,
+    error::{VkError, check_vk_success},
+    find_name, log_target, sort_names,
+};
 
 const VALIDATION_LAYER_NAMES: [&CStr; ValidationLayer::COUNT] = [
     c"VK_LAYER_KHRONOS_validation",
+    c"VK_LAYER_KHRONOS_profiles",
     c"__UNKNOW_LAYER",
     c"__UNREACHABLE_LAYER",
 ];
+const SORTED_VALIDATION_LAYER_NAMES: [usize; ValidationLayer::COUNT] =
+    sort_names(&VALIDATION_LAYER_NAMES);
 
 /// Enumeration of all supported validation layers, plus UnknownLayer and UnreachableLayer
-#[derive(Clone, Copy, strum::EnumCount, strum::EnumIter, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, strum::EnumCount, strum::EnumIter, strum::FromRepr, PartialEq, Eq, Debug)]
 #[repr(usize)]
 pub enum ValidationLayer {
     KhronosValidation,
+    /// Simulates a device described by a Vulkan profiles JSON file, clamping every capability
+    /// query, including the crate's, to it. The file is selected with
+    /// InstanceCreateInfo::builder().profile_file(..), or with the layer's own settings, e.g. the
+    /// VK_KHRONOS_PROFILES_PROFILE_FILE environment variable
+    KhronosProfiles,
     UnknownLayer,
     UnreachableLayer,
 }
@@ -29,9 +45,13 @@ impl ValidationLayer {
 
     /// Return the first enum variant with name mathing the given string. Returns ValidationLayer::UnknownLayer if the name doesn't match any variant
     pub fn identify_name(name: &CStr) -> Self {
-        Self::iter()
-            .find(|layer| layer.name() == name)
-            .unwrap_or(ValidationLayer::UnknownLayer)
+        find_name(
+            &VALIDATION_LAYER_NAMES,
+            &SORTED_VALIDATION_LAYER_NAMES,
+            name,
+        )
+        .and_then(Self::from_repr)
+        .unwrap_or(ValidationLayer::UnknownLayer)
     }
 }
 
@@ -42,11 +62,42 @@ pub struct AvailableValidationLayer {
     layer: ValidationLayer,
     spec_version: u32,
     implementation_version: u32,
-    name: CString,
+    /// Borrows the static name of known layers, only unknown names are allocated
+    name: Cow<'static, CStr>,
     description: CString,
 }
 
 impl AvailableValidationLayer {
+    /// None if the name or the description is not NUL terminated. Such layers can not be
+    /// enabled, so they are skipped with a warning
+    fn from_properties(prop: &vk::LayerProperties) -> Option<Self> {
+        let (raw_name, description) = match (
+            prop.layer_name_as_c_str(),
+            prop.description_as_c_str(),
+        ) {
+            (Ok(name), Ok(description)) => (name, description.to_owned()),
+            (Err(e), _) | (_, Err(e)) => {
+                log::warn!(
+                    target: log_target::INSTANCE,
+                    "[instance.invalid_name] Skipped an enumerated layer with an invalid name or description: {e}"
+                );
+                return None;
+            }
+        };
+        let layer = ValidationLayer::identify_name(raw_name);
+        let name = match layer {
+            ValidationLayer::UnknownLayer => Cow::Owned(CString::from(raw_name)),
+            _ => Cow::Borrowed(layer.name()),
+        };
+        Some(Self {
+            layer,
+            name,
+            description,
+            spec_version: prop.spec_version,
+            implementation_version: prop.implementation_version,
+        })
+    }
+
     /// Returns the layer variant
     pub fn layer(&self) -> ValidationLayer {
         self.layer
@@ -70,49 +121,45 @@ impl AvailableValidationLayer {
         self.implementation_version
     }
 }
-/// Enumerates available instance validation layers. Ignores unkwown names.
+/// Enumerates available instance validation layers. Layers with unknown names are kept as
+/// UnknownLayer along with their driver name and description, so they can still be enabled with
+/// add_by_name
 pub fn enumerate() -> Vec<AvailableValidationLayer> {
+    try_enumerate().unwrap_or_else(|e| e.fatal())
+}
+
+/// Enumerates available instance validation layers. Layers with unknown names are kept as
+/// UnknownLayer along with their driver name and description, so they can still be enabled with
+/// add_by_name
+pub fn try_enumerate() -> Result<Vec<AvailableValidationLayer>, VkError> {
     // Safety: ENTRY is never destroyed
-    let layers = expect_vk_success("Failed to enumerate validation layers", unsafe {
+    let layers = check_vk_success("Failed to enumerate validation layers", unsafe {
         entry::ENTRY.enumerate_instance_layer_properties()
-    });
+    })?;
 
     let layers = layers
-        .into_iter()
-        .flat_map(|prop| {
-            let name = prop
-                .layer_name_as_c_str()
-                .expect("Got invalid layer name from enumeration")
-                .to_owned();
-            let description = prop
-                .description_as_c_str()
-                .expect("Got invalid layer description from enumeration")
-                .to_owned();
-            let layer = ValidationLayer::identify_name(&name);
-            Some(AvailableValidationLayer {
-                layer,
-                name,
-                description,
-                spec_version: prop.spec_version,
-                implementation_version: prop.implementation_version,
-            })
-        })
+        .iter()
+        .filter_map(AvailableValidationLayer::from_properties)
         .collect();
-    log::trace!("Enumerated validation layers, avalilable layers: {layers:#?}");
-    layers
+    log::trace!(
+        target: log_target::INSTANCE,
+        "[instance.layers] Enumerated validation layers, avalilable layers: {layers:#?}"
+    );
+    Ok(layers)
 }
 
 /// List of some of the available validation layers. Guarantees avalilability. Used to safely
-/// enable those layers without additional checks
+/// enable those layers without additional checks. Layers are kept in insertion order and enabled
+/// in that order, the first layer being the closest to the application
 #[derive(Debug, Default)]
 pub struct AvailableValidationLayers {
     layers: Vec<AvailableValidationLayer>,
 }
 
 impl AvailableValidationLayers {
-    /// Returns Vec of contained layers' names
-    pub fn names(&self) -> Vec<&CStr> {
-        self.layers.iter().map(|layer| layer.name()).collect()
+    /// Iterates over the contained layers' names in enabling order without allocating
+    pub fn names(&self) -> impl ExactSizeIterator<Item = &CStr> {
+        self.layers.iter().map(|layer| layer.name())
     }
 
     /// Slice of avalilable validation layers
@@ -125,8 +172,36 @@ impl AvailableValidationLayers {
         self.layers.push(layer);
     }
 
+    /// Adds the layer from available with the given driver name. Works for layers unknown to this
+    /// crate too, e.g. vendor-internal ones. Returns false if no such layer is available
+    pub fn add_by_name(&mut self, available: &[AvailableValidationLayer], name: &CStr) -> bool {
+        let Some(layer) = available.iter().find(|avail| avail.name() == name) else {
+            return false;
+        };
+        self.add(layer.clone());
+        true
+    }
+
+    /// Moves the layer with the given driver name to the front, so it is the closest to the
+    /// application. Keeps the order of the other layers. Returns false if there is no such layer
+    pub fn move_to_front(&mut self, name: &CStr) -> bool {
+        let Some(pos) = self.layers.iter().position(|layer| layer.name() == name) else {
+            return false;
+        };
+        self.layers[..=pos].rotate_right(1);
+        true
+    }
+
+    /// Sorts the layers by ascending priority key. Layers with equal keys keep their order
+    pub fn sort_by_priority<K: Ord>(
+        &mut self,
+        priority: impl FnMut(&AvailableValidationLayer) -> K,
+    ) {
+        self.layers.sort_by_key(priority);
+    }
+
     /// If avalilable contains each element from required, returns Self containing all required
-    /// layers, else returns None
+    /// layers in the order of required, else returns None
     pub fn from_available_and_required(
         available: &[AvailableValidationLayer],
         required: &[ValidationLayer],
@@ -145,6 +220,9 @@ impl AvailableValidationLayers {
     }
 }
 
+const _: () = assert_send_sync::<AvailableValidationLayer>();
+const _: () = assert_send_sync::<AvailableValidationLayers>();
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -160,12 +238,231 @@ mod test {
         assert_eq!(layer, ValidationLayer::KhronosValidation);
     }
 
+    #[test]
+    fn identify_all() {
+        use strum::IntoEnumIterator;
+
+        for layer in ValidationLayer::iter() {
+            assert_eq!(ValidationLayer::identify_name(layer.name()), layer);
+        }
+        assert!(SORTED_VALIDATION_LAYER_NAMES.is_sorted_by_key(|&i| VALIDATION_LAYER_NAMES[i]));
+    }
+
     #[test]
     fn identify_not_found() {
         let layer = ValidationLayer::identify_name(c"garbage");
         assert_eq!(layer, ValidationLayer::UnknownLayer);
     }
 
+    #[test]
+    fn profile_settings() {
+        use std::path::Path;
+
+        use ash::vk;
+
+        use crate::vk::instance::{InstanceCreateInfo, InstanceCreateInfoError};
+
+        let info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_3)
+            .profile_file(Path::new("roadmap.json"))
+            .build();
+        assert!(matches!(
+            info,
+            Err(InstanceCreateInfoError::ProfilesLayerNotEnabled)
+        ));
+
+        let mut layers = AvailableValidationLayers::default();
+        layers.add(AvailableValidationLayer {
+            layer: ValidationLayer::KhronosProfiles,
+            spec_version: 1,
+            implementation_version: 1,
+            name: Cow::Borrowed(ValidationLayer::KhronosProfiles.name()),
+            description: c"Khronos profiles layer".to_owned(),
+        });
+        let info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_3)
+            .validation_layers(layers)
+            .profile_file(Path::new("roadmap.json"))
+            .profile_name(b"VP_KHR_roadmap_2022")
+            .build()
+            .unwrap();
+        let raw = info.create_raw();
+        let create_info = raw.vk_instance_create_info();
+        assert!(!create_info.p_next.is_null());
+
+        // Safety: the chain points into raw, which is alive
+        unsafe {
+            let settings_info = &*create_info.p_next.cast::<vk::LayerSettingsCreateInfoEXT>();
+            assert_eq!(
+                settings_info.s_type,
+                vk::StructureType::LAYER_SETTINGS_CREATE_INFO_EXT
+            );
+            let settings = std::slice::from_raw_parts(
+                settings_info.p_settings,
+                settings_info.setting_count as usize,
+            );
+            let value = |name: &CStr| {
+                let setting = settings
+                    .iter()
+                    .find(|setting| CStr::from_ptr(setting.p_setting_name) == name)
+                    .unwrap();
+                assert_eq!(
+                    CStr::from_ptr(setting.p_layer_name),
+                    c"VK_LAYER_KHRONOS_profiles"
+                );
+                let values = std::slice::from_raw_parts(
+                    setting.p_values.cast::<*const std::ffi::c_char>(),
+                    setting.value_count as usize,
+                );
+                values
+                    .iter()
+                    .map(|&value| CStr::from_ptr(value))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(value(c"profile_file"), [c"roadmap.json"]);
+            assert_eq!(value(c"profile_name"), [c"VP_KHR_roadmap_2022"]);
+            assert!(value(c"simulate_capabilities").contains(&c"SIMULATE_FEATURES_BIT"));
+        }
+
+        // Without a profile nothing is chained
+        let info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_3)
+            .build()
+            .unwrap();
+        assert!(info.create_raw().vk_instance_create_info().p_next.is_null());
+    }
+
+    fn unknown_layer(name: &CStr) -> AvailableValidationLayer {
+        AvailableValidationLayer {
+            layer: ValidationLayer::UnknownLayer,
+            spec_version: 1,
+            implementation_version: 2,
+            name: Cow::Owned(name.to_owned()),
+            description: c"Vendor layer".to_owned(),
+        }
+    }
+
+    #[test]
+    fn names_do_not_allocate() {
+        use ash::vk;
+
+        use crate::{alloc_counter::allocations_during, vk::instance::InstanceCreateInfo};
+
+        // Up to 8 name pointers are stored inline by RawInstanceCreateInfo
+        let mut layers = AvailableValidationLayers::default();
+        for name in [
+            c"VK_LAYER_VENDOR_0",
+            c"VK_LAYER_VENDOR_1",
+            c"VK_LAYER_VENDOR_2",
+            c"VK_LAYER_VENDOR_3",
+            c"VK_LAYER_VENDOR_4",
+            c"VK_LAYER_VENDOR_5",
+            c"VK_LAYER_VENDOR_6",
+            c"VK_LAYER_VENDOR_7",
+        ] {
+            layers.add(unknown_layer(name));
+        }
+
+        let (count, allocations) = allocations_during(|| layers.names().count());
+        assert_eq!(count, 8);
+        assert_eq!(allocations, 0);
+
+        let info = InstanceCreateInfo::builder()
+            .api_version(vk::API_VERSION_1_1)
+            .validation_layers(layers)
+            .build()
+            .unwrap();
+        let (enabled, allocations) = allocations_during(|| {
+            info.create_raw()
+                .vk_instance_create_info()
+                .enabled_layer_count
+        });
+        assert_eq!(enabled, 8);
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn skip_invalid_name() {
+        let mut prop = vk::LayerProperties::default()
+            .layer_name(c"VK_LAYER_KHRONOS_validation")
+            .unwrap();
+        assert_eq!(
+            AvailableValidationLayer::from_properties(&prop)
+                .unwrap()
+                .layer(),
+            ValidationLayer::KhronosValidation
+        );
+
+        prop.description.fill(b'a' as _);
+        assert!(AvailableValidationLayer::from_properties(&prop).is_none());
+
+        let mut prop = vk::LayerProperties::default();
+        prop.layer_name.fill(b'a' as _);
+        assert!(AvailableValidationLayer::from_properties(&prop).is_none());
+    }
+
+    #[test]
+    fn add_unknown_by_name() {
+        let available = [unknown_layer(c"VK_LAYER_VENDOR_internal")];
+
+        let mut res = AvailableValidationLayers::default();
+        assert!(!res.add_by_name(&available, c"VK_LAYER_VENDOR_missing"));
+        assert!(res.add_by_name(&available, c"VK_LAYER_VENDOR_internal"));
+
+        assert!(res.names().eq([c"VK_LAYER_VENDOR_internal"]));
+        assert_eq!(res.layers()[0].layer(), ValidationLayer::UnknownLayer);
+        assert_eq!(res.layers()[0].description(), c"Vendor layer");
+    }
+
+    #[test]
+    fn ordering() {
+        let mut layers = AvailableValidationLayers::default();
+        for name in [c"VK_LAYER_a", c"VK_LAYER_b", c"VK_LAYER_c", c"VK_LAYER_d"] {
+            layers.add(unknown_layer(name));
+        }
+
+        assert!(layers.move_to_front(c"VK_LAYER_c"));
+        assert!(!layers.move_to_front(c"VK_LAYER_missing"));
+        assert!(
+            layers
+                .names()
+                .eq([c"VK_LAYER_c", c"VK_LAYER_a", c"VK_LAYER_b", c"VK_LAYER_d"])
+        );
+
+        // Stable: a and d keep their relative order
+        layers.sort_by_priority(|layer| {
+            layer.name() != c"VK_LAYER_b" && layer.name() != c"VK_LAYER_c"
+        });
+        assert!(
+            layers
+                .names()
+                .eq([c"VK_LAYER_c", c"VK_LAYER_b", c"VK_LAYER_a", c"VK_LAYER_d"])
+        );
+
+        // The order is passed through to instance creation
+        let info = crate::vk::instance::InstanceCreateInfo::builder()
+            .validation_layers(layers)
+            .api_version(ash::vk::API_VERSION_1_0)
+            .build()
+            .unwrap();
+        let raw = info.create_raw();
+        let create_info = raw.vk_instance_create_info();
+        // Safety: the pointers point into info, which is alive
+        let names: Vec<_> = unsafe {
+            std::slice::from_raw_parts(
+                create_info.pp_enabled_layer_names,
+                create_info.enabled_layer_count as usize,
+            )
+        }
+        .iter()
+        .map(|&name| unsafe { CStr::from_ptr(name) })
+        .collect();
+        assert_eq!(
+            names,
+            &[c"VK_LAYER_c", c"VK_LAYER_b", c"VK_LAYER_a", c"VK_LAYER_d"]
+        );
+    }
+
     #[test]
     fn has_khronos() {
         let available = enumerate();
@@ -194,7 +491,7 @@ mod test {
         let mut res = AvailableValidationLayers::default();
         res.add(khr);
 
-        assert_eq!(res.names(), &[c"VK_LAYER_KHRONOS_validation"]);
+        assert!(res.names().eq([c"VK_LAYER_KHRONOS_validation"]));
     }
 
     #[test]
@@ -215,6 +512,6 @@ mod test {
         let res =
             AvailableValidationLayers::from_available_and_required(&available, &required).unwrap();
 
-        assert_eq!(&res.names(), &[c"VK_LAYER_KHRONOS_validation"]);
+        assert!(res.names().eq([c"VK_LAYER_KHRONOS_validation"]));
     }
 }
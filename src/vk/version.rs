@@ -0,0 +1,105 @@
+//!
+//! Vulkan API version handling
+//!
+
+use std::fmt::{Debug, Display};
+
+use ash::vk;
+
+/// Vulkan API version, packed the same way as vk::make_api_version does
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiVersion(u32);
+
+impl ApiVersion {
+    pub const V1_0: Self = Self(vk::API_VERSION_1_0);
+    pub const V1_1: Self = Self(vk::API_VERSION_1_1);
+    pub const V1_2: Self = Self(vk::API_VERSION_1_2);
+    pub const V1_3: Self = Self(vk::API_VERSION_1_3);
+
+    /// Wraps a packed version number
+    pub const fn from_raw(version: u32) -> Self {
+        Self(version)
+    }
+
+    /// Packed version number
+    pub const fn raw(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn major(&self) -> u32 {
+        vk::api_version_major(self.0)
+    }
+
+    pub const fn minor(&self) -> u32 {
+        vk::api_version_minor(self.0)
+    }
+
+    pub const fn patch(&self) -> u32 {
+        vk::api_version_patch(self.0)
+    }
+
+    /// Returns UnsupportedVersion if self is lower than required
+    pub fn require(self, required: ApiVersion) -> Result<(), UnsupportedVersion> {
+        if self >= required {
+            Ok(())
+        } else {
+            Err(UnsupportedVersion {
+                required,
+                available: self,
+            })
+        }
+    }
+}
+
+impl Display for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major(), self.minor(), self.patch())
+    }
+}
+
+impl Debug for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ApiVersion({self})")
+    }
+}
+
+/// Returned by the wrappers that need a newer Vulkan version than the one in use and have no
+/// extension to fall back to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("Vulkan {required} is required, but only {available} is available")]
+pub struct UnsupportedVersion {
+    pub required: ApiVersion,
+    pub available: ApiVersion,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn components() {
+        let version = ApiVersion::from_raw(vk::make_api_version(0, 1, 2, 198));
+        assert_eq!(version.major(), 1);
+        assert_eq!(version.minor(), 2);
+        assert_eq!(version.patch(), 198);
+        assert_eq!(version.to_string(), "1.2.198");
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(ApiVersion::V1_0 < ApiVersion::V1_1);
+        assert!(ApiVersion::V1_3 > ApiVersion::from_raw(vk::make_api_version(0, 1, 2, 198)));
+    }
+
+    #[test]
+    fn require() {
+        assert!(ApiVersion::V1_2.require(ApiVersion::V1_1).is_ok());
+        assert_eq!(
+            ApiVersion::V1_0.require(ApiVersion::V1_3),
+            Err(UnsupportedVersion {
+                required: ApiVersion::V1_3,
+                available: ApiVersion::V1_0
+            })
+        );
+    }
+}